@@ -0,0 +1,21 @@
+@async
+Feature: Async
+
+    With the "async" feature enabled, bootstrap, sync and head_oid
+    can be awaited from within an async runtime
+
+    Background:
+
+        Given I have a remote Git repository available
+
+    Example: Bootstrap and sync asynchronously
+
+        Given I have no directory called "gitsync"
+        When I bootstrap asynchronously
+        Then the bootstrap completes
+        And the repository is cloned
+        Given there are new commits on the remote
+        When I sync asynchronously
+        Then the sync reports changes
+        And there are changes
+        And head_oid_async matches HEAD
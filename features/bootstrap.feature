@@ -13,6 +13,7 @@ Feature: Bootstrap
         When I bootstrap
         Then the repository is cloned
         And the bootstrap completes
+        And the history lists 1 commit
 
     Example: Local directory isn't a Git repository
 
@@ -0,0 +1,35 @@
+Feature: Cancellation
+
+    Cancelling the token given to GitSync interrupts a clone or fetch
+    and leaves the repository as it was before
+
+    Background:
+
+        Given I have a remote Git repository available
+
+    Example: Cancelled before bootstrapping
+
+        Given I have no directory called "gitsync"
+        And the operation has been cancelled
+        When I bootstrap
+        Then the bootstrap errors because "cancelled"
+        And there is no directory called "gitsync"
+
+    Example: Cancelling a slow clone
+
+        Given the remote is served over SSH
+        And I have an SSH private key
+        And the remote takes 1 second to respond
+        And I have no directory called "gitsync"
+        When I bootstrap over SSH and cancel after 100 milliseconds
+        Then the bootstrap errors because "cancelled"
+        And there is no directory called "gitsync"
+
+    Example: Cancelled sync
+
+        Given I have a Git repository in a directory called "gitsync"
+        And there are remote changes
+        And the operation has been cancelled
+        When I sync
+        Then the sync errors because "cancelled"
+        And there is no change
@@ -0,0 +1,42 @@
+Feature: Atomic deployments
+
+    GitSync can deploy each commit into a directory of its own and swap a
+    symlink over to it, so that readers never see a half-updated tree
+
+    Background:
+
+        Given I have a remote Git repository available
+        And I deploy to "current" keeping 1 previous revision
+
+    Example: Bootstrap deploys the cloned commit
+
+        Given I have no directory called "gitsync"
+        When I bootstrap
+        Then the bootstrap completes
+        And "current" points at the commit the clone is at
+        And there is 1 deployed revision
+
+    Example: Sync swaps the symlink to the new commit
+
+        Given I have a Git repository in a directory called "gitsync"
+        When I sync
+        Then the sync completes
+        And "current" points at the commit the clone is at
+        Given there are new commits on the remote
+        When I sync
+        Then the sync completes
+        And "current" points at the commit the clone is at
+        And the sync reports the deployment
+        And there are 2 deployed revisions
+
+    Example: Old revisions are removed
+
+        Given I have a Git repository in a directory called "gitsync"
+        When I sync
+        Given there are new commits on the remote
+        When I sync
+        Given there are new commits on the remote
+        When I sync
+        Then the sync completes
+        And "current" points at the commit the clone is at
+        And there are 2 deployed revisions
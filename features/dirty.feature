@@ -0,0 +1,51 @@
+Feature: Dirty worktree
+
+    GitSync can discard local changes that would stop it from syncing,
+    after stashing them or backing them up
+
+    Background:
+
+        Given I have a remote Git repository available
+        And I have a Git repository in a directory called "gitsync"
+        And there are remote changes
+        And there are local changes
+        And there is an untracked file called "stray/file.txt"
+
+    Example: Refuse to sync a dirty worktree by default
+
+        When I sync
+        Then the sync errors because "the worktree isn't clean"
+        And the sync error mentions "modified: file"
+        And the sync error mentions "untracked: stray/file.txt"
+        And there is no change
+        And the worktree has "file" containing "123"
+
+    Example: Discard local changes
+
+        Given I discard local changes
+        When I sync
+        Then the sync completes
+        And there are changes
+        And the worktree is clean
+        And the worktree has "file" containing "12"
+        And there is no directory called "gitsync/stray"
+
+    Example: Stash local changes
+
+        Given I stash local changes
+        When I sync
+        Then the sync completes
+        And there are changes
+        And the worktree is clean
+        And the stash has "file" containing "123"
+        And the stash has "stray/file.txt" containing "untracked"
+
+    Example: Back up local changes
+
+        Given I back up local changes to "backups"
+        When I sync
+        Then the sync completes
+        And there are changes
+        And the worktree is clean
+        And the backup in "backups" has "file" containing "123"
+        And the backup in "backups" has "stray/file.txt" containing "untracked"
@@ -0,0 +1,57 @@
+Feature: Divergence
+
+    GitSync can follow a remote branch that was force-pushed, or that
+    diverged from local commits, by resetting, rebasing or merging
+
+    Background:
+
+        Given I have a remote Git repository available
+        And I have a Git repository in a directory called "gitsync"
+
+    Example: Refuse to follow a force-push by default
+
+        Given the remote is force-pushed
+        When I sync
+        Then the sync errors because "fast-forward not possible"
+        And there is no change
+
+    Example: Follow a force-push with a hard reset
+
+        Given I follow force-pushes by "hard-reset"
+        And the remote is force-pushed
+        When I sync
+        Then the sync completes
+        And the clone is at the latest remote commit
+        And the sync reports rewritten history
+        And the sync reports these changed files:
+            | change   | path |
+            | modified | file |
+
+    Example: Rebase local commits onto a force-push
+
+        Given I follow force-pushes by "rebase"
+        And there are local commits
+        And the remote is force-pushed
+        When I sync
+        Then the sync completes
+        And the local commits are rebased onto the remote
+        And the sync reports rewritten history
+
+    Example: Merge the remote into local commits
+
+        Given I follow force-pushes by "merge"
+        And there are local commits
+        And there are new commits on the remote
+        When I sync
+        Then the sync completes
+        And the remote is merged into the local commits
+        And the sync reports history wasn't rewritten
+
+    Example: Refuse to rebase conflicting changes
+
+        Given I follow force-pushes by "rebase"
+        And there is a conflicting local commit
+        And there are new commits on the remote
+        When I sync
+        Then the sync errors because "conflicting changes"
+        And there is no change
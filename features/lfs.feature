@@ -0,0 +1,91 @@
+@lfs
+Feature: Git LFS
+
+    With the "lfs" feature enabled, GitSync can replace the pointer files
+    committed in place of Git LFS objects with the objects themselves
+
+    Background:
+
+        Given I have a remote Git repository available
+        And the remote has an LFS server
+        And the remote has an LFS file "assets/logo.bin" containing "not a pointer"
+        And I download LFS objects
+
+    Example: Bootstrap downloads LFS objects
+
+        Given I have no directory called "gitsync"
+        When I bootstrap
+        Then the bootstrap completes
+        And the worktree has "assets/logo.bin" containing "not a pointer"
+
+    Example: Sync downloads LFS objects and keeps the worktree clean
+
+        Given I have a Git repository in a directory called "gitsync"
+        When I sync
+        Then the sync completes
+        And the worktree has "assets/logo.bin" containing "not a pointer"
+        Given there are new commits on the remote
+        And the remote has an LFS file "assets/banner.bin" containing "also not a pointer"
+        When I sync
+        Then the sync completes
+        And the worktree has "assets/logo.bin" containing "not a pointer"
+        And the worktree has "assets/banner.bin" containing "also not a pointer"
+
+    Example: Deployments get the downloaded objects
+
+        Given I have no directory called "gitsync"
+        And I deploy to "current" keeping 1 previous revision
+        When I bootstrap
+        Then the bootstrap completes
+        And the deployment has "assets/logo.bin" containing "not a pointer"
+
+    Example: LFS servers that need credentials get them
+
+        Given the remote has an LFS server requiring the password "secret"
+        And the remote has an LFS file "assets/banner.bin" containing "also not a pointer"
+        And I have a Git repository in a directory called "gitsync"
+        And I use the password "secret"
+        When I sync
+        Then the sync completes
+        And the worktree has "assets/banner.bin" containing "also not a pointer"
+
+    Example: Sync fails when the LFS server turns the credentials down
+
+        Given the remote has an LFS server requiring the password "secret"
+        And I have a Git repository in a directory called "gitsync"
+        And I use the password "wrong"
+        When I sync
+        Then the sync errors because "LFS download failed"
+
+    Example: LFS servers get the credentials from credential helpers
+
+        Given the remote has an LFS server requiring the password "secret"
+        And the remote has an LFS file "assets/banner.bin" containing "also not a pointer"
+        And I have a Git repository in a directory called "gitsync"
+        And I use a credential helper answering the password "secret"
+        When I sync
+        Then the sync completes
+        And the worktree has "assets/banner.bin" containing "also not a pointer"
+        And the credential helper was asked to "store" the password
+
+    Example: Credential helpers erase the credentials the LFS server turns down
+
+        Given the remote has an LFS server requiring the password "secret"
+        And I have a Git repository in a directory called "gitsync"
+        And I use a credential helper answering the password "wrong"
+        When I sync
+        Then the sync errors because "LFS download failed"
+        And the credential helper was asked to "erase" the password
+
+    Example: Credentials read from a file are read again once rotated
+
+        Given the remote has an LFS server requiring the password "secret"
+        And the remote has an LFS file "assets/banner.bin" containing "also not a pointer"
+        And I have a Git repository in a directory called "gitsync"
+        And the credentials file contains the password "expired"
+        When I sync
+        Then the sync errors because "LFS download failed"
+        Given the credentials file contains the password "secret"
+        When I sync
+        Then the sync completes
+        And the worktree has "assets/banner.bin" containing "also not a pointer"
@@ -0,0 +1,43 @@
+Feature: Pinned revision
+
+    GitSync can pin the worktree to a commit, while still reporting how
+    far the remote has moved ahead of it
+
+    Background:
+
+        Given I have a remote Git repository available
+        And I pin the first remote commit
+
+    Example: Sync a clone pinned to an older commit
+
+        Given I have a Git repository in a directory called "gitsync"
+        And there are new commits on the remote
+        When I sync
+        Then the sync completes
+        And the pinned commit is checked out
+        And the sync reports a drift of 2 commits
+        Given there are new commits on the remote
+        When I sync
+        Then the sync completes
+        And the pinned commit is checked out
+        And the sync reports no changed files
+        And the sync reports a drift of 3 commits
+
+    Example: Bootstrap a pinned clone
+
+        Given I have no directory called "gitsync"
+        When I bootstrap
+        Then the bootstrap completes
+        And the pinned commit is checked out
+        When I sync
+        Then the sync completes
+        And the sync reports a drift of 1 commit
+
+    Example: The pinned commit doesn't exist
+
+        Given I have a Git repository in a directory called "gitsync"
+        And there are no remote changes
+        And I pin commit "0123456789abcdef0123456789abcdef01234567"
+        When I sync
+        Then the sync errors because "unknown revision"
+        And there is no change
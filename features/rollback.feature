@@ -0,0 +1,75 @@
+Feature: Rollback
+
+    GitSync records every commit it checks out, and can roll back to any
+    of them without touching the remote. Syncs stay on the rolled back
+    commit until upstream moves past the one that was rolled back from
+
+    Background:
+
+        Given I have a remote Git repository available
+        And I have a Git repository in a directory called "gitsync"
+        And there are new commits on the remote
+        When I sync
+
+    Example: Roll back to the commit before the last sync
+
+        When I roll back 1 sync
+        Then the sync completes
+        And there is no change
+        And the history lists 3 commits
+        And the latest history entry is a rollback
+
+    Example: A rollback holds until syncing is resumed
+
+        When I roll back 1 sync
+        And I sync
+        Then the sync completes
+        And the clone is back at the commit it started out at
+        And the sync reports a drift of 1 commit
+        When I resume syncing
+        And I sync
+        Then the sync completes
+        And the clone is at the latest remote commit
+
+    Example: A rollback holds until upstream moves past the rolled back commit
+
+        When I roll back 1 sync
+        Given there are new commits on the remote
+        When I sync
+        Then the sync completes
+        And the clone is at the latest remote commit
+
+    Example: A watcher keeps to a rollback
+
+        When I roll back 1 sync
+        And I watch the repository until 2 syncs are reported
+        Then the watcher reported 2 syncs
+        And the clone is back at the commit it started out at
+
+    Example: Rolling back again goes further back
+
+        Given there are new commits on the remote
+        When I sync
+        And I roll back 1 sync
+        And I roll back 1 sync
+        Then the sync completes
+        And the clone is back at the commit it started out at
+        When I roll back 1 sync
+        Then the sync errors because "rollback not possible"
+
+    Example: Roll back further than the history goes
+
+        When I roll back 2 syncs
+        Then the sync errors because "rollback not possible"
+        And the clone is at the latest remote commit
+        And the history lists 2 commits
+
+    Example: Rolling back redeploys the earlier commit
+
+        Given I deploy to "current" keeping 1 previous revision
+        And there are new commits on the remote
+        When I sync
+        And I roll back 1 sync
+        Then the sync completes
+        And "current" points at the commit the clone is at
+        And the sync reports the deployment
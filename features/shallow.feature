@@ -0,0 +1,29 @@
+Feature: Shallow clones
+
+    GitSync can bootstrap with only part of the history, which
+    later syncs keep shallow
+
+    Background:
+
+        Given I have a remote Git repository available
+        And I have no directory called "gitsync"
+
+    Example: Bootstrap and sync a shallow clone
+
+        When I bootstrap with a depth of 1
+        Then the bootstrap completes
+        And the clone is shallow with 1 commit
+        Given there are new commits on the remote
+        When I sync
+        Then the sync reports changes
+        And there are changes
+        And the clone is shallow with 2 commits
+
+    Example: Local commits beyond what a shallow clone can tell apart
+
+        When I bootstrap with a depth of 1
+        Then the bootstrap completes
+        Given there are local commits
+        And there are new commits on the remote
+        When I sync
+        Then the sync errors because "insufficient history"
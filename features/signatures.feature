@@ -0,0 +1,74 @@
+Feature: Commit signatures
+
+    GitSync can refuse to check out commits that aren't signed by a
+    trusted SSH or GPG key
+
+    Background:
+
+        Given I have a remote Git repository available
+        And I trust commits signed with SSH key "trusted"
+
+    Example: Sync a commit signed by a trusted SSH key
+
+        Given I have a Git repository in a directory called "gitsync"
+        And the remote commits are signed with SSH key "trusted"
+        And there are new commits on the remote
+        When I sync
+        Then the sync completes
+        And the clone is at the latest remote commit
+
+    Example: Sync a commit signed by a trusted GPG key
+
+        Given I trust commits signed with GPG key "release"
+        And I have a Git repository in a directory called "gitsync"
+        And the remote commits are signed with GPG key "release"
+        And there are new commits on the remote
+        When I sync
+        Then the sync completes
+        And the clone is at the latest remote commit
+
+    Example: Refuse to sync an unsigned commit
+
+        Given I have a Git repository in a directory called "gitsync"
+        And there are new commits on the remote
+        When I sync
+        Then the sync errors because "untrusted commit"
+        And there is no change
+
+    Example: Refuse to sync a commit signed by an untrusted key
+
+        Given I have a Git repository in a directory called "gitsync"
+        And the remote commits are signed with SSH key "untrusted"
+        And there are new commits on the remote
+        When I sync
+        Then the sync errors because "untrusted commit"
+        And there is no change
+
+    Example: Refuse to bootstrap an unsigned commit
+
+        Given I have no directory called "gitsync"
+        When I bootstrap
+        Then the bootstrap errors because "untrusted commit"
+        And there is no directory called "gitsync"
+
+    Rule: Every new commit can be verified, rather than only the tip
+
+        Background:
+
+            Given I have a Git repository in a directory called "gitsync"
+            And there are new commits on the remote
+            And the remote commits are signed with SSH key "trusted"
+            And there are new commits on the remote
+
+        Example: Only the tip is verified by default
+
+            When I sync
+            Then the sync completes
+            And the clone is at the latest remote commit
+
+        Example: Refuse to sync when an earlier commit is unsigned
+
+            Given I verify every commit
+            When I sync
+            Then the sync errors because "untrusted commit"
+            And there is no change
@@ -0,0 +1,41 @@
+Feature: Sparse checkout
+
+    GitSync can check out only some directories of the repository,
+    and keeps to them when syncing
+
+    Background:
+
+        Given I have a remote Git repository available
+        And the remote commits these changes:
+            | change | path                        |
+            | add    | deploy/kustomization.yaml   |
+            | add    | deploy/prod/app.yaml        |
+            | add    | deploy/prod/db/values.yaml  |
+            | add    | deploy/staging/app.yaml     |
+            | add    | docs/index.md               |
+        And I have no directory called "gitsync"
+
+    Example: Bootstrap and sync a sparse checkout
+
+        Given I only check out "deploy/prod"
+        When I bootstrap
+        Then the bootstrap completes
+        And the worktree contains "file"
+        And the worktree contains "deploy/kustomization.yaml"
+        And the worktree contains "deploy/prod/app.yaml"
+        And the worktree contains "deploy/prod/db/values.yaml"
+        And the worktree doesn't contain "deploy/staging/app.yaml"
+        And the worktree doesn't contain "docs/index.md"
+        Given the remote commits these changes:
+            | change | path                    |
+            | modify | deploy/prod/app.yaml    |
+            | modify | deploy/staging/app.yaml |
+            | add    | deploy/prod/new.yaml    |
+            | add    | docs/new.md             |
+        When I sync
+        Then the sync completes
+        And the clone is at the latest remote commit
+        And the worktree contains "deploy/prod/new.yaml"
+        And the worktree has the latest "deploy/prod/app.yaml"
+        And the worktree doesn't contain "deploy/staging/app.yaml"
+        And the worktree doesn't contain "docs/new.md"
@@ -0,0 +1,86 @@
+Feature: SSH
+
+    GitSync authenticates against SSH remotes with the configured
+    private key, which may be given inline or as a path to a key file
+
+    Background:
+
+        Given I have a remote Git repository available
+        And the remote is served over SSH
+        And I have no directory called "gitsync"
+
+    Example: Inline private key
+
+        Given I have an SSH private key
+        When I bootstrap over SSH with the inline private key
+        Then the bootstrap completes
+        And the repository is cloned
+
+    Example: Private key file protected by a passphrase
+
+        Given I have an SSH private key protected by "hunter2"
+        When I bootstrap over SSH with the private key file
+        Then the bootstrap completes
+        And the repository is cloned
+        Given there are new commits on the remote
+        When I sync over SSH
+        Then the sync reports changes
+        And there are changes
+
+    Example: Inline private key protected by a passphrase
+
+        Given I have an SSH private key protected by "hunter2"
+        When I bootstrap over SSH with the inline private key
+        Then the bootstrap completes
+
+    Example: Inline legacy PEM private key protected by a passphrase
+
+        Given I have a legacy PEM private key protected by "hunter2"
+        When I bootstrap over SSH with the inline private key
+        Then the bootstrap completes
+        And the repository is cloned
+
+    Example: Wrong passphrase
+
+        Given I have an SSH private key protected by "hunter2"
+        And the passphrase is "letmein"
+        When I bootstrap over SSH with the private key file
+        Then the bootstrap errors because "invalid private key"
+
+    Example: No private key
+
+        When I bootstrap over SSH without a private key
+        Then the bootstrap errors
+
+    Rule: Pinned host keys must match the key presented by the server
+
+        Background:
+
+            Given the SSH server has a host key
+            And I have an SSH private key
+
+        Example: Host key is trusted
+
+            Given I trust the SSH server's host key
+            When I bootstrap over SSH with the private key file
+            Then the bootstrap completes
+            Given there are new commits on the remote
+            When I sync over SSH
+            Then the sync reports changes
+
+        Example: Host key is unknown
+
+            Given I trust a different host key
+            When I bootstrap over SSH with the private key file
+            Then the bootstrap errors because "host key mismatch"
+            And there is no directory called "gitsync"
+
+        Example: Host key changes after bootstrap
+
+            Given I trust the SSH server's host key
+            When I bootstrap over SSH with the private key file
+            Then the bootstrap completes
+            Given I trust a different host key
+            And there are new commits on the remote
+            When I sync over SSH
+            Then the sync errors because "host key mismatch"
@@ -0,0 +1,39 @@
+Feature: Submodules
+
+    GitSync can clone and update a repository's submodules, recursively,
+    along with the repository itself
+
+    Background:
+
+        Given I have a remote Git repository available
+        And the remote has a submodule at "lib"
+        And I update submodules
+
+    Example: Bootstrap clones submodules
+
+        Given I have no directory called "gitsync"
+        When I bootstrap
+        Then the bootstrap completes
+        And the submodule "lib" is at its latest commit
+
+    Example: Sync clones and updates submodules
+
+        Given I have a Git repository in a directory called "gitsync"
+        When I sync
+        Then the sync completes
+        And the sync reports submodule "lib" cloned
+        And the submodule "lib" is at its latest commit
+        Given the submodule "lib" has new commits
+        When I sync
+        Then the sync completes
+        And the sync reports submodule "lib" updated
+        And the submodule "lib" is at its latest commit
+
+    Example: Submodules of submodules are cloned too
+
+        Given the submodule "lib" has a submodule at "vendor"
+        And I have a Git repository in a directory called "gitsync"
+        When I sync
+        Then the sync completes
+        And the sync reports submodule "lib/vendor" cloned
+        And the submodule "lib/vendor" is at its latest commit
@@ -23,8 +23,20 @@ Feature: Sync
         Then the sync completes
         And there is no change
         And the sync reports no changes
+        And the sync reports no changed files
         And head_oid matches HEAD
 
+    @serial
+    Example: Syncing without git installed
+
+        Given I have no directory called "gitsync"
+        When I bootstrap without git on the PATH
+        Then the bootstrap completes
+        Given there are new commits on the remote
+        When I sync without git on the PATH
+        Then the sync completes
+        And the clone is at the latest remote commit
+
     Example: Local Changes
 
         Given I have a Git repository in a directory called "gitsync"
@@ -47,3 +59,62 @@ Feature: Sync
         And the checked out branch is "config"
         When I sync branch "config"
         Then the sync reports no changes
+
+    Example: Changed files are reported
+
+        Given the remote commits these changes:
+            | change | path             |
+            | add    | deploy/old.yaml  |
+            | add    | deploy/gone.yaml |
+        And I have a Git repository in a directory called "gitsync"
+        And the remote commits these changes:
+            | change | path                                 |
+            | modify | file                                 |
+            | add    | deploy/new.yaml                      |
+            | delete | deploy/gone.yaml                     |
+            | rename | deploy/old.yaml -> deploy/moved.yaml |
+        When I sync
+        Then the sync completes
+        And the sync reports these changed files:
+            | change   | path                                 |
+            | modified | file                                 |
+            | added    | deploy/new.yaml                      |
+            | deleted  | deploy/gone.yaml                     |
+            | renamed  | deploy/old.yaml -> deploy/moved.yaml |
+        And the worktree contains "deploy/moved.yaml"
+        And the worktree doesn't contain "deploy/gone.yaml"
+        And the worktree doesn't contain "deploy/old.yaml"
+        And the worktree is clean
+
+    Rule: Only changes to watched paths are reported
+
+        Background:
+
+            Given I have a Git repository in a directory called "gitsync"
+            And I only watch "deploy/prod/**"
+            And I don't watch "**/*.md"
+
+        Example: Changes inside and outside of watched paths
+
+            Given the remote commits these changes:
+                | change | path                    |
+                | add    | deploy/prod/app.yaml    |
+                | add    | deploy/prod/README.md   |
+                | add    | deploy/staging/app.yaml |
+            When I sync
+            Then the sync completes
+            And the sync reports these changed files:
+                | change | path                 |
+                | added  | deploy/prod/app.yaml |
+            And the clone is at the latest remote commit
+
+        Example: Changes only outside of watched paths
+
+            Given the remote commits these changes:
+                | change | path                    |
+                | add    | deploy/staging/app.yaml |
+                | modify | file                    |
+            When I sync
+            Then the sync completes
+            And the sync reports no watched changes
+            And the clone is at the latest remote commit
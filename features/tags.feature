@@ -0,0 +1,72 @@
+Feature: Tags
+
+    GitSync can follow a tag, or the highest version matching a semver
+    requirement, instead of a branch
+
+    Background:
+
+        Given I have a remote Git repository available
+        And the remote has a release tagged "v1.0.0"
+        And the remote has a release tagged "v1.2.0"
+        And the remote has a release tagged "v2.0.0"
+
+    Example: Bootstrap and sync the highest matching version
+
+        Given I follow versions ">=1.0, <2"
+        And I have no directory called "gitsync"
+        When I bootstrap
+        Then the bootstrap completes
+        And the checked out tag is "v1.2.0"
+        Given the remote has a release tagged "v1.3.0"
+        When I sync
+        Then the sync completes
+        And the sync reports tag "v1.3.0"
+        And the sync reports these changed files:
+            | change   | path    |
+            | modified | version |
+        And the checked out tag is "v1.3.0"
+
+    Example: A tag deleted upstream is no longer followed
+
+        Given I follow versions ">=1.0, <2"
+        And I have no directory called "gitsync"
+        When I bootstrap
+        Then the bootstrap completes
+        And the checked out tag is "v1.2.0"
+        Given the remote deletes the tag "v1.2.0"
+        When I sync
+        Then the sync completes
+        And the sync reports tag "v1.0.0"
+        And the checked out tag is "v1.0.0"
+
+    Example: Follow an exact tag
+
+        Given I follow tag "v1.0.0"
+        And I have no directory called "gitsync"
+        When I bootstrap
+        Then the bootstrap completes
+        And the checked out tag is "v1.0.0"
+        Given the remote has a release tagged "v1.0.1"
+        When I sync
+        Then the sync completes
+        And the sync reports tag "v1.0.0"
+        And the sync reports no changed files
+        And the checked out tag is "v1.0.0"
+
+    Example: Move a branch clone back to an older release
+
+        Given I have a Git repository in a directory called "gitsync"
+        And I follow versions "^1"
+        When I sync
+        Then the sync completes
+        And the sync reports tag "v1.2.0"
+        And the checked out tag is "v1.2.0"
+
+    Example: No tag matches
+
+        Given I have a Git repository in a directory called "gitsync"
+        And there are no remote changes
+        And I follow versions ">=3"
+        When I sync
+        Then the sync errors because "no matching tag"
+        And there is no change
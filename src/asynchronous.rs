@@ -0,0 +1,61 @@
+use crate::errors::GitSyncError;
+use crate::{CancellationToken, GitSync, Oid, SyncOutcome, SyncRecord};
+
+/// Async equivalents of the blocking operations, for use from a tokio runtime.
+///
+/// The work happens on tokio's blocking thread pool. Dropping one of the
+/// returned futures cancels a clone or fetch that is still in flight rather
+/// than leaving it running in the background, without cancelling
+/// `GitSync::cancellation` itself.
+impl GitSync {
+    pub async fn bootstrap_async(&self) -> Result<(), GitSyncError> {
+        self.spawn_blocking(GitSync::bootstrap).await
+    }
+
+    pub async fn sync_async(&self) -> Result<SyncOutcome, GitSyncError> {
+        self.spawn_blocking(GitSync::sync).await
+    }
+
+    pub async fn history_async(&self) -> Result<Vec<SyncRecord>, GitSyncError> {
+        self.spawn_blocking(GitSync::history).await
+    }
+
+    pub async fn rollback_async(&self, steps: usize) -> Result<SyncOutcome, GitSyncError> {
+        self.spawn_blocking(move |gitsync| gitsync.rollback(steps))
+            .await
+    }
+
+    pub async fn resume_async(&self) -> Result<(), GitSyncError> {
+        self.spawn_blocking(GitSync::resume).await
+    }
+
+    pub async fn head_oid_async(&self) -> Result<Option<Oid>, GitSyncError> {
+        self.spawn_blocking(GitSync::head_oid).await
+    }
+
+    async fn spawn_blocking<T, F>(&self, operation: F) -> Result<T, GitSyncError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&GitSync) -> Result<T, GitSyncError> + Send + 'static,
+    {
+        let mut gitsync = self.clone();
+        gitsync.cancellation = self.cancellation.child_token();
+        let _guard = CancelOnDrop(gitsync.cancellation.clone());
+
+        match tokio::task::spawn_blocking(move || operation(&gitsync)).await {
+            Ok(result) => result,
+            Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+            Err(error) => Err(GitSyncError::GenericError {
+                error: std::io::Error::other(error),
+            }),
+        }
+    }
+}
+
+struct CancelOnDrop(CancellationToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
@@ -0,0 +1,399 @@
+use crate::errors::ConfigurationError;
+use crate::secret::redact_credentials;
+use crate::{
+    CancellationToken, CredentialHelpers, CredentialProvider, Deployment, DirtyWorktreePolicy,
+    DivergenceStrategy, GitSync, Oid, Secret, Shallow, SignaturePolicy, TagSelector,
+};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Builds a `GitSync`, checking that its configuration makes sense before
+/// anything is cloned or fetched.
+#[derive(Clone, Debug)]
+pub struct GitSyncBuilder {
+    gitsync: GitSync,
+}
+
+impl GitSync {
+    /// Start building a `GitSync` that clones `repo` into `dir`.
+    pub fn builder(repo: impl Into<String>, dir: impl Into<PathBuf>) -> GitSyncBuilder {
+        GitSyncBuilder::new(repo, dir)
+    }
+}
+
+impl GitSyncBuilder {
+    pub fn new(repo: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
+        GitSyncBuilder {
+            gitsync: GitSync {
+                repo: repo.into(),
+                dir: dir.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn with_branch(mut self, branch: impl Into<String>) -> Self {
+        self.gitsync.branch = Some(branch.into());
+        self
+    }
+
+    pub fn with_username(mut self, username: impl Into<String>) -> Self {
+        self.gitsync.username = Some(username.into());
+        self
+    }
+
+    /// Can't be combined with `with_token`.
+    pub fn with_password(mut self, password: impl Into<String>) -> Self {
+        self.gitsync.password = Some(Secret::new(password));
+        self
+    }
+
+    /// Can't be combined with `with_password`.
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.gitsync.token = Some(Secret::new(token));
+        self
+    }
+
+    /// Can't be combined with `with_password` or `with_token`.
+    pub fn with_credential_helpers(mut self, credential_helpers: CredentialHelpers) -> Self {
+        self.gitsync.credential_helpers = Some(credential_helpers);
+        self
+    }
+
+    /// Can't be combined with `with_password`, `with_token` or
+    /// `with_credential_helpers`.
+    pub fn with_credential_provider(
+        mut self,
+        credential_provider: impl CredentialProvider + 'static,
+    ) -> Self {
+        self.gitsync.credential_provider = Some(Arc::new(credential_provider));
+        self
+    }
+
+    pub fn with_private_key(mut self, private_key: impl Into<String>) -> Self {
+        self.gitsync.private_key = Some(Secret::new(private_key));
+        self
+    }
+
+    /// Needs `with_private_key`.
+    pub fn with_passphrase(mut self, passphrase: impl Into<String>) -> Self {
+        self.gitsync.passphrase = Some(Secret::new(passphrase));
+        self
+    }
+
+    pub fn with_known_hosts(mut self, known_hosts: Vec<String>) -> Self {
+        self.gitsync.known_hosts = known_hosts;
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.gitsync.cancellation = cancellation;
+        self
+    }
+
+    pub fn with_include(mut self, include: Vec<String>) -> Self {
+        self.gitsync.include = include;
+        self
+    }
+
+    pub fn with_exclude(mut self, exclude: Vec<String>) -> Self {
+        self.gitsync.exclude = exclude;
+        self
+    }
+
+    pub fn with_shallow(mut self, shallow: Shallow) -> Self {
+        self.gitsync.shallow = Some(shallow);
+        self
+    }
+
+    pub fn with_sparse_checkout(mut self, sparse_checkout: Vec<String>) -> Self {
+        self.gitsync.sparse_checkout = sparse_checkout;
+        self
+    }
+
+    pub fn with_signature_policy(mut self, signature_policy: SignaturePolicy) -> Self {
+        self.gitsync.signature_policy = Some(signature_policy);
+        self
+    }
+
+    pub fn with_tag(mut self, tag: TagSelector) -> Self {
+        self.gitsync.tag = Some(tag);
+        self
+    }
+
+    pub fn with_revision(mut self, revision: Oid) -> Self {
+        self.gitsync.revision = Some(revision);
+        self
+    }
+
+    pub fn with_divergence(mut self, divergence: DivergenceStrategy) -> Self {
+        self.gitsync.divergence = divergence;
+        self
+    }
+
+    pub fn with_dirty_worktree(mut self, dirty_worktree: DirtyWorktreePolicy) -> Self {
+        self.gitsync.dirty_worktree = dirty_worktree;
+        self
+    }
+
+    pub fn with_deployment(mut self, deployment: Deployment) -> Self {
+        self.gitsync.deployment = Some(deployment);
+        self
+    }
+
+    pub fn with_submodules(mut self, submodules: bool) -> Self {
+        self.gitsync.submodules = submodules;
+        self
+    }
+
+    #[cfg(feature = "lfs")]
+    pub fn with_lfs(mut self, lfs: bool) -> Self {
+        self.gitsync.lfs = lfs;
+        self
+    }
+
+    pub fn build(self) -> Result<GitSync, ConfigurationError> {
+        validate(self.gitsync)
+    }
+}
+
+/// Check that `gitsync` is configured in a way that can work.
+pub(crate) fn validate(gitsync: GitSync) -> Result<GitSync, ConfigurationError> {
+    if gitsync.repo.trim().is_empty() {
+        return Err(ConfigurationError::MissingRepository);
+    }
+    let reason = match gix::url::parse(gitsync.repo.as_str().into()) {
+        Err(error) => Some(error.to_string()),
+        // Anything without a scheme parses as a local path, so only let that
+        // through when there is something to clone at that path
+        Ok(url)
+            if url.scheme == gix::url::Scheme::File
+                && !gitsync.repo.contains("://")
+                && !Path::new(&gitsync.repo).exists() =>
+        {
+            Some("it is neither a URL nor an existing local path".to_owned())
+        }
+        Ok(_) => None,
+    };
+    if let Some(reason) = reason {
+        return Err(ConfigurationError::InvalidRepository {
+            repo: redact_credentials(&gitsync.repo).into_owned(),
+            reason: redact_credentials(&reason).into_owned(),
+        });
+    }
+
+    if gitsync.dir.as_os_str().is_empty() {
+        return Err(ConfigurationError::MissingDirectory);
+    }
+    if gitsync.dir.exists() && !gitsync.dir.is_dir() {
+        return Err(ConfigurationError::InvalidDirectory { dir: gitsync.dir });
+    }
+
+    if let Some(branch) = &gitsync.branch {
+        // git itself keeps branches from looking like options or `HEAD`
+        let reason = if branch.starts_with('-') || branch == "HEAD" {
+            Some(format!("git doesn't allow {branch} as a branch name"))
+        } else {
+            gix::refs::FullName::try_from(format!("refs/heads/{branch}").as_str())
+                .err()
+                .map(|error| error.to_string())
+        };
+
+        if let Some(reason) = reason {
+            return Err(ConfigurationError::InvalidBranch {
+                branch: branch.clone(),
+                reason,
+            });
+        }
+    }
+
+    if gitsync.token.is_some() && gitsync.password.is_some() {
+        return Err(ConfigurationError::ConflictingCredentials {
+            first: "token",
+            second: "password",
+        });
+    }
+    for (first, set) in [
+        ("credential_provider", gitsync.credential_provider.is_some()),
+        ("credential_helpers", gitsync.credential_helpers.is_some()),
+    ] {
+        if !set {
+            continue;
+        }
+
+        for (second, conflicts) in [
+            ("credential_helpers", gitsync.credential_helpers.is_some()),
+            ("token", gitsync.token.is_some()),
+            ("password", gitsync.password.is_some()),
+        ] {
+            if conflicts && first != second {
+                return Err(ConfigurationError::ConflictingCredentials { first, second });
+            }
+        }
+    }
+    if gitsync.passphrase.is_some() && gitsync.private_key.is_none() {
+        return Err(ConfigurationError::MissingCredential {
+            credential: "passphrase",
+            requires: "private_key",
+        });
+    }
+
+    Ok(gitsync)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileCredentials;
+
+    #[test]
+    fn valid_configurations_are_built() {
+        let gitsync = GitSync::builder("https://example.com/org/app.git", "/srv/app")
+            .with_branch("release/1.x")
+            .with_username("octocat")
+            .with_token("secret-token")
+            .build()
+            .unwrap();
+
+        assert_eq!(gitsync.repo, "https://example.com/org/app.git");
+        assert_eq!(gitsync.dir, PathBuf::from("/srv/app"));
+        assert_eq!(gitsync.branch.as_deref(), Some("release/1.x"));
+        assert_eq!(gitsync.token.unwrap().expose(), "secret-token");
+    }
+
+    #[test]
+    fn the_repository_and_directory_are_required() {
+        assert_eq!(
+            ConfigurationError::MissingRepository,
+            GitSync::builder(" ", "/srv/app").build().unwrap_err()
+        );
+        assert_eq!(
+            ConfigurationError::MissingDirectory,
+            GitSync::builder("https://example.com/app.git", "")
+                .build()
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn the_directory_must_not_be_a_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        assert_eq!(
+            ConfigurationError::InvalidDirectory {
+                dir: file.path().to_owned()
+            },
+            GitSync::builder("https://example.com/app.git", file.path())
+                .build()
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn repositories_must_be_urls_or_existing_paths() {
+        for repo in ["not a url", "ftp//", "/does/not/exist.git"] {
+            let error = GitSync::builder(repo, "/srv/app").build().unwrap_err();
+
+            assert!(
+                matches!(error, ConfigurationError::InvalidRepository { .. }),
+                "{:?} was accepted",
+                repo
+            );
+        }
+
+        let local = tempfile::tempdir().unwrap();
+        for repo in [
+            "https://example.com/org/app.git",
+            "ssh://git@example.com/org/app.git",
+            "git@example.com:org/app.git",
+            "file:///srv/app.git",
+            local.path().to_str().unwrap(),
+        ] {
+            assert!(
+                GitSync::builder(repo, "/srv/app").build().is_ok(),
+                "{:?} was turned down",
+                repo
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_repositories_say_why_they_were_turned_down() {
+        assert_eq!(
+            ConfigurationError::InvalidRepository {
+                repo: "not a url".to_owned(),
+                reason: "it is neither a URL nor an existing local path".to_owned(),
+            },
+            GitSync::builder("not a url", "/srv/app")
+                .build()
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn invalid_branch_names_are_turned_down() {
+        for branch in ["", "has space", "double..dot", "ends.lock", "-f", "HEAD"] {
+            let error = GitSync::builder("https://example.com/app.git", "/srv/app")
+                .with_branch(branch)
+                .build()
+                .unwrap_err();
+
+            assert!(
+                matches!(error, ConfigurationError::InvalidBranch { .. }),
+                "{:?} was accepted",
+                branch
+            );
+        }
+    }
+
+    #[test]
+    fn credentials_that_exclude_each_other_are_turned_down() {
+        assert_eq!(
+            ConfigurationError::ConflictingCredentials {
+                first: "token",
+                second: "password"
+            },
+            GitSync::builder("https://example.com/app.git", "/srv/app")
+                .with_token("secret-token")
+                .with_password("secret")
+                .build()
+                .unwrap_err()
+        );
+        assert_eq!(
+            ConfigurationError::ConflictingCredentials {
+                first: "credential_helpers",
+                second: "password"
+            },
+            GitSync::builder("https://example.com/app.git", "/srv/app")
+                .with_credential_helpers(CredentialHelpers::default())
+                .with_password("secret")
+                .build()
+                .unwrap_err()
+        );
+        assert_eq!(
+            ConfigurationError::ConflictingCredentials {
+                first: "credential_provider",
+                second: "token"
+            },
+            GitSync::builder("https://example.com/app.git", "/srv/app")
+                .with_credential_provider(FileCredentials {
+                    username: None,
+                    password_file: PathBuf::from("/run/secrets/token"),
+                })
+                .with_token("secret-token")
+                .build()
+                .unwrap_err()
+        );
+        assert_eq!(
+            ConfigurationError::MissingCredential {
+                credential: "passphrase",
+                requires: "private_key"
+            },
+            GitSync::builder("git@example.com:app.git", "/srv/app")
+                .with_passphrase("secret")
+                .build()
+                .unwrap_err()
+        );
+    }
+}
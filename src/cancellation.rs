@@ -0,0 +1,130 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::Duration;
+
+/// A handle that interrupts the clone or fetch of a `GitSync` while it runs.
+///
+/// Clones of a token share their state, so cancelling any clone cancels them
+/// all. Cancelling a token also cancels every token created from it with
+/// `child_token`, but not the other way around.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    children: Mutex<Vec<Weak<Inner>>>,
+    wake: Condvar,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Create a token that is cancelled along with this one, but that can also
+    /// be cancelled on its own.
+    pub fn child_token(&self) -> CancellationToken {
+        let child = Arc::new(Inner::default());
+
+        let mut children = self.inner.lock_children();
+        children.retain(|child| child.strong_count() > 0);
+        children.push(Arc::downgrade(&child));
+
+        // Checked while holding the lock, so a concurrent cancel can't miss the child
+        if self.is_cancelled() {
+            child.cancelled.store(true, Ordering::SeqCst);
+        }
+
+        CancellationToken { inner: child }
+    }
+
+    /// Block for up to `timeout`, returning early with `true` if the token is
+    /// cancelled in the meantime.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let children = self.inner.lock_children();
+        let _children = self
+            .inner
+            .wake
+            .wait_timeout_while(children, timeout, |_| !self.is_cancelled())
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        self.is_cancelled()
+    }
+
+    /// The flag handed to gix, which it polls to know when to give up.
+    pub(crate) fn as_interrupt(&self) -> &AtomicBool {
+        &self.inner.cancelled
+    }
+}
+
+impl Inner {
+    fn cancel(&self) {
+        let children = {
+            let mut children = self.lock_children();
+            self.cancelled.store(true, Ordering::SeqCst);
+            self.wake.notify_all();
+            std::mem::take(&mut *children)
+        };
+
+        for child in children.iter().filter_map(Weak::upgrade) {
+            child.cancel();
+        }
+    }
+
+    fn lock_children(&self) -> std::sync::MutexGuard<'_, Vec<Weak<Inner>>> {
+        self.children
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelling_a_token_cancels_its_children() {
+        let parent = CancellationToken::new();
+        let child = parent.child_token();
+        let grandchild = child.child_token();
+
+        parent.cancel();
+
+        assert!(child.is_cancelled());
+        assert!(grandchild.is_cancelled());
+        assert!(parent.child_token().is_cancelled());
+    }
+
+    #[test]
+    fn cancelling_a_child_leaves_its_parent_alone() {
+        let parent = CancellationToken::new();
+        let child = parent.child_token();
+
+        child.cancel();
+
+        assert!(child.is_cancelled());
+        assert!(!parent.is_cancelled());
+    }
+
+    #[test]
+    fn wait_timeout_returns_when_cancelled() {
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+
+        let handle = std::thread::spawn(move || canceller.cancel());
+
+        assert!(token.wait_timeout(Duration::from_secs(60)));
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+use crate::errors::GitSyncError;
+use crate::Oid;
+use gix::bstr::ByteSlice;
+use gix::glob::wildmatch;
+use gix::object::tree::diff::ChangeDetached;
+use gix::Repository;
+use std::path::{Path, PathBuf};
+
+/// A file that differs between the `previous` and `current` commit of a sync.
+///
+/// Paths are relative to the root of the repository, and ids are those of the
+/// blobs before and after the change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileChange {
+    Added {
+        path: PathBuf,
+        id: Oid,
+    },
+    Modified {
+        path: PathBuf,
+        previous_id: Oid,
+        id: Oid,
+    },
+    Deleted {
+        path: PathBuf,
+        previous_id: Oid,
+    },
+    Renamed {
+        previous_path: PathBuf,
+        path: PathBuf,
+        previous_id: Oid,
+        id: Oid,
+    },
+}
+
+impl FileChange {
+    /// The path of the file after the change, or before it for deletions.
+    pub fn path(&self) -> &Path {
+        match self {
+            FileChange::Added { path, .. }
+            | FileChange::Modified { path, .. }
+            | FileChange::Deleted { path, .. }
+            | FileChange::Renamed { path, .. } => path,
+        }
+    }
+
+    /// The path of a renamed file before it was renamed.
+    pub fn previous_path(&self) -> Option<&Path> {
+        match self {
+            FileChange::Renamed { previous_path, .. } => Some(previous_path),
+            _ => None,
+        }
+    }
+}
+
+/// Keep only the changes to watched paths: those matching one of the `include`
+/// patterns (or any path, if there are none) and none of the `exclude` ones.
+/// A rename is kept when the file was watched on either side of it.
+pub(crate) fn retain_watched(
+    changes: &mut Vec<FileChange>,
+    include: &[String],
+    exclude: &[String],
+) {
+    changes.retain(|change| {
+        is_watched(change.path(), include, exclude)
+            || change
+                .previous_path()
+                .is_some_and(|path| is_watched(path, include, exclude))
+    });
+}
+
+fn is_watched(path: &Path, include: &[String], exclude: &[String]) -> bool {
+    let path = gix::path::to_unix_separators_on_windows(gix::path::into_bstr(path));
+    let matches = |pattern: &String| {
+        wildmatch(
+            pattern.as_bytes().as_bstr(),
+            path.as_ref(),
+            wildmatch::Mode::NO_MATCH_SLASH_LITERAL,
+        )
+    };
+
+    (include.is_empty() || include.iter().any(matches)) && !exclude.iter().any(matches)
+}
+
+/// Diff the trees of `previous` and `current`, treating a missing `previous`
+/// as an empty tree so that every file shows up as added.
+pub(crate) fn diff(
+    repository: &Repository,
+    previous: Option<Oid>,
+    current: Oid,
+) -> Result<Vec<FileChange>, GitSyncError> {
+    let tree_of = |id: Oid| {
+        repository
+            .find_commit(id)
+            .map_err(GitSyncError::from_gix)?
+            .tree()
+            .map_err(GitSyncError::from_gix)
+    };
+
+    let previous_tree = previous.map(tree_of).transpose()?;
+    let current_tree = tree_of(current)?;
+
+    // Without explicit options, rename tracking follows the repository's
+    // `diff.renames` configuration, defaulting to git's own behaviour.
+    let changes = repository
+        .diff_tree_to_tree(previous_tree.as_ref(), &current_tree, None)
+        .map_err(GitSyncError::from_gix)?;
+
+    Ok(changes
+        .into_iter()
+        .filter(|change| !change.entry_mode().is_tree())
+        .map(FileChange::from)
+        .collect())
+}
+
+impl From<ChangeDetached> for FileChange {
+    fn from(change: ChangeDetached) -> Self {
+        let path = |location: &gix::bstr::BStr| gix::path::from_bstr(location).into_owned();
+
+        match change {
+            ChangeDetached::Addition { location, id, .. } => FileChange::Added {
+                path: path(location.as_ref()),
+                id,
+            },
+            ChangeDetached::Deletion { location, id, .. } => FileChange::Deleted {
+                path: path(location.as_ref()),
+                previous_id: id,
+            },
+            ChangeDetached::Modification {
+                location,
+                previous_id,
+                id,
+                ..
+            } => FileChange::Modified {
+                path: path(location.as_ref()),
+                previous_id,
+                id,
+            },
+            // A copy leaves its source in place, so it only adds a file
+            ChangeDetached::Rewrite {
+                location,
+                id,
+                copy: true,
+                ..
+            } => FileChange::Added {
+                path: path(location.as_ref()),
+                id,
+            },
+            ChangeDetached::Rewrite {
+                source_location,
+                source_id,
+                location,
+                id,
+                ..
+            } => FileChange::Renamed {
+                previous_path: path(source_location.as_ref()),
+                path: path(location.as_ref()),
+                previous_id: source_id,
+                id,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    #[test]
+    fn everything_is_watched_without_patterns() {
+        assert!(is_watched(Path::new("deploy/prod/app.yaml"), &[], &[]));
+    }
+
+    #[test]
+    fn include_patterns_match_across_directories() {
+        let include = patterns(&["deploy/prod/**"]);
+
+        assert!(is_watched(Path::new("deploy/prod/app.yaml"), &include, &[]));
+        assert!(is_watched(
+            Path::new("deploy/prod/db/values.yaml"),
+            &include,
+            &[]
+        ));
+        assert!(!is_watched(
+            Path::new("deploy/staging/app.yaml"),
+            &include,
+            &[]
+        ));
+    }
+
+    #[test]
+    fn single_star_stays_within_a_directory() {
+        let include = patterns(&["deploy/*.yaml"]);
+
+        assert!(is_watched(Path::new("deploy/app.yaml"), &include, &[]));
+        assert!(!is_watched(
+            Path::new("deploy/prod/app.yaml"),
+            &include,
+            &[]
+        ));
+    }
+
+    #[test]
+    fn exclude_patterns_win_over_include_patterns() {
+        let include = patterns(&["deploy/**"]);
+        let exclude = patterns(&["**/*.md"]);
+
+        assert!(is_watched(Path::new("deploy/app.yaml"), &include, &exclude));
+        assert!(!is_watched(
+            Path::new("deploy/README.md"),
+            &include,
+            &exclude
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::dirty::DirtyPaths;
+use crate::errors::GitSyncError;
+use crate::{sparse, Oid};
+use gix::bstr::BStr;
+use gix::index::entry::{Flags, Mode};
+use gix::Repository;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+/// Update the index and worktree to `commit`, as `git reset --hard` would,
+/// keeping to the repository's sparse checkout.
+///
+/// Only the files that differ from the current index are written, along with
+/// the `rewrite` paths, whose local changes are thrown away. Files the commit
+/// doesn't have are removed; untracked files are left alone.
+pub(crate) fn reset(
+    repository: &Repository,
+    commit: Oid,
+    rewrite: &DirtyPaths,
+) -> Result<(), GitSyncError> {
+    let workdir = repository.workdir().expect("syncs always have a worktree");
+    let previous = repository
+        .index_or_empty()
+        .map_err(GitSyncError::from_gix)?;
+    let tree_id = repository
+        .find_commit(commit)
+        .map_err(GitSyncError::from_gix)?
+        .tree_id()
+        .map_err(GitSyncError::from_gix)?;
+    let mut index = repository
+        .index_from_tree(&tree_id)
+        .map_err(GitSyncError::from_gix)?;
+
+    let directories = sparse::directories(repository)?;
+    if !directories.is_empty() {
+        sparse::skip_outside_of_cone(&mut index, &directories);
+    }
+
+    for entry in previous.entries() {
+        if entry.flags.contains(Flags::SKIP_WORKTREE) || entry.mode == Mode::COMMIT {
+            continue;
+        }
+
+        let path = entry.path(&previous);
+        let checked_out = index
+            .entry_by_path(path)
+            .is_some_and(|entry| !entry.flags.contains(Flags::SKIP_WORKTREE));
+        if !checked_out {
+            remove_file(workdir, path)?;
+        }
+    }
+
+    // Skip the files that are already checked out, restoring their flags
+    // once the others are written
+    let mut unchanged = Vec::new();
+    for (position, (entry, path)) in index.entries_mut_with_paths().enumerate() {
+        if entry.flags.contains(Flags::SKIP_WORKTREE) || rewrite.contains_key(path) {
+            continue;
+        }
+
+        let current = previous.entry_by_path(path).filter(|current| {
+            current.id == entry.id
+                && current.mode == entry.mode
+                && !current.flags.contains(Flags::SKIP_WORKTREE)
+        });
+        if let Some(current) = current {
+            entry.stat = current.stat;
+            unchanged.push((position, entry.flags));
+            entry.flags.insert(Flags::EXTENDED | Flags::SKIP_WORKTREE);
+        }
+    }
+
+    let mut options = repository
+        .checkout_options(gix::worktree::stack::state::attributes::Source::IdMapping)
+        .map_err(GitSyncError::from_gix)?;
+    options.overwrite_existing = true;
+
+    // Stopping halfway would leave a mix of both commits behind
+    gix::worktree::state::checkout(
+        &mut index,
+        workdir,
+        repository
+            .objects
+            .clone()
+            .into_arc()
+            .map_err(GitSyncError::from_gix)?,
+        &gix::progress::Discard,
+        &gix::progress::Discard,
+        &AtomicBool::new(false),
+        options,
+    )
+    .map_err(GitSyncError::from_gix)?;
+
+    let entries = index.entries_mut();
+    for (position, flags) in unchanged {
+        entries[position].flags = flags;
+    }
+
+    index
+        .write(Default::default())
+        .map_err(GitSyncError::from_gix)?;
+
+    Ok(())
+}
+
+/// Remove the file at `path` from the worktree, along with any directories it
+/// leaves empty.
+pub(crate) fn remove_file(workdir: &Path, path: &BStr) -> Result<(), GitSyncError> {
+    let file = workdir.join(gix::path::from_bstr(path));
+    match std::fs::remove_file(&file) {
+        Ok(()) => {}
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(GitSyncError::GenericError { error }),
+    }
+
+    // Like `git clean -d`, don't leave empty directories behind
+    for directory in file.ancestors().skip(1) {
+        if directory == workdir || std::fs::remove_dir(directory).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,560 @@
+use crate::builder;
+use crate::errors::ConfigurationError;
+use crate::secret;
+use crate::{
+    CredentialHelpers, DirtyWorktreePolicy, DivergenceStrategy, GitSync, Oid, Secret, Shallow,
+    SignaturePolicy, TagSelector,
+};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The prefix of the environment variables read by `GitSync::from_env`.
+pub const ENV_PREFIX: &str = "GITSYNC_";
+
+impl GitSync {
+    /// Load the configuration from `GITSYNC_*` environment variables, then
+    /// validate it as `GitSyncBuilder::build` would.
+    ///
+    /// Each field is read from the variable named after it, e.g. `GITSYNC_REPO`,
+    /// `GITSYNC_DIR` and `GITSYNC_SPARSE_CHECKOUT`, with lists separated by
+    /// commas (or new lines, for `GITSYNC_KNOWN_HOSTS` and
+    /// `GITSYNC_CREDENTIAL_HELPER_PROGRAMS`). The secrets can be
+    /// read from a file instead, such as a mounted Kubernetes secret, named by
+    /// the variable with a `_FILE` suffix (e.g. `GITSYNC_TOKEN_FILE`). Any other
+    /// `GITSYNC_*` variable is turned down, as it is most likely misspelt.
+    pub fn from_env() -> Result<GitSync, ConfigurationError> {
+        let mut vars = Vec::new();
+        for (name, value) in std::env::vars_os() {
+            let name = match name.into_string() {
+                Ok(name) if name.starts_with(ENV_PREFIX) => name,
+                _ => continue,
+            };
+            let value = value
+                .into_string()
+                .map_err(|_| invalid(&name, "it isn't valid UTF-8"))?;
+            vars.push((name, value));
+        }
+
+        GitSync::default().merge_env(vars)
+    }
+
+    /// Override this configuration, e.g. one deserialised from a file, with
+    /// the `GITSYNC_*` variables among `vars`, as `from_env` reads them, then
+    /// validate it.
+    pub fn merge_env<I, K, V>(mut self, vars: I) -> Result<GitSync, ConfigurationError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let env = Env {
+            vars: vars
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+                .collect(),
+            read: RefCell::default(),
+        };
+
+        if let Some(repo) = env.get("REPO") {
+            self.repo = repo.to_owned();
+        }
+        if let Some(dir) = env.get("DIR") {
+            self.dir = PathBuf::from(dir);
+        }
+        if let Some(branch) = env.get("BRANCH") {
+            self.branch = Some(branch.to_owned());
+        }
+        if let Some(username) = env.get("USERNAME") {
+            self.username = Some(username.to_owned());
+        }
+
+        for (key, secret) in [
+            ("PASSWORD", &mut self.password),
+            ("TOKEN", &mut self.token),
+            ("PASSPHRASE", &mut self.passphrase),
+            ("PRIVATE_KEY", &mut self.private_key),
+        ] {
+            if let Some(value) = env.secret(key)? {
+                *secret = Some(Secret::from(value));
+            }
+        }
+
+        let credential_helpers = env.bool("CREDENTIAL_HELPERS")?;
+        let programs = env.lines("CREDENTIAL_HELPER_PROGRAMS");
+        match (credential_helpers, programs) {
+            (Some(false), _) => self.credential_helpers = None,
+            (None, None) => {}
+            (configured, programs) => {
+                let helpers = self
+                    .credential_helpers
+                    .get_or_insert_with(CredentialHelpers::default);
+                if let Some(configured) = configured {
+                    helpers.configured = configured;
+                }
+                if let Some(programs) = programs {
+                    helpers.programs = programs;
+                }
+            }
+        }
+
+        if let Some(known_hosts) = env.lines("KNOWN_HOSTS") {
+            self.known_hosts = known_hosts;
+        }
+        for (key, list) in [
+            ("INCLUDE", &mut self.include),
+            ("EXCLUDE", &mut self.exclude),
+            ("SPARSE_CHECKOUT", &mut self.sparse_checkout),
+        ] {
+            if let Some(values) = env.list(key) {
+                *list = values;
+            }
+        }
+
+        match (
+            env.parse::<NonZeroU32>("SHALLOW_DEPTH")?,
+            env.parse::<u64>("SHALLOW_SINCE")?,
+        ) {
+            (Some(_), Some(_)) => return Err(env.conflict("SHALLOW_DEPTH", "SHALLOW_SINCE")),
+            (Some(depth), None) => self.shallow = Some(Shallow::Depth(depth)),
+            (None, Some(seconds)) => self.shallow = Some(Shallow::Since(since(seconds))),
+            (None, None) => {}
+        }
+
+        match (
+            env.get("TAG"),
+            env.parse::<semver::VersionReq>("TAG_VERSION")?,
+        ) {
+            (Some(_), Some(_)) => return Err(env.conflict("TAG", "TAG_VERSION")),
+            (Some(tag), None) => self.tag = Some(TagSelector::Exact(tag.to_owned())),
+            (None, Some(requirement)) => self.tag = Some(TagSelector::SemVer(requirement)),
+            (None, None) => {}
+        }
+
+        if let Some(revision) = env.get("REVISION") {
+            self.revision =
+                Some(parse_revision(revision).map_err(|reason| env.invalid("REVISION", reason))?);
+        }
+
+        if let Some(divergence) = env.get("DIVERGENCE") {
+            self.divergence = match divergence {
+                "fail" => DivergenceStrategy::Fail,
+                "hard_reset" => DivergenceStrategy::HardReset,
+                "rebase" => DivergenceStrategy::Rebase,
+                "merge" => DivergenceStrategy::Merge,
+                _ => {
+                    return Err(
+                        env.invalid("DIVERGENCE", "expected fail, hard_reset, rebase or merge")
+                    )
+                }
+            };
+        }
+
+        if let Some(dirty_worktree) = env.get("DIRTY_WORKTREE") {
+            self.dirty_worktree = match dirty_worktree.split_once(':') {
+                Some(("backup", dir)) if !dir.is_empty() => {
+                    DirtyWorktreePolicy::Backup(PathBuf::from(dir))
+                }
+                None if dirty_worktree == "fail" => DirtyWorktreePolicy::Fail,
+                None if dirty_worktree == "discard" => DirtyWorktreePolicy::Discard,
+                None if dirty_worktree == "stash" => DirtyWorktreePolicy::Stash,
+                _ => {
+                    return Err(env.invalid(
+                        "DIRTY_WORKTREE",
+                        "expected fail, discard, stash or backup:<directory>",
+                    ))
+                }
+            };
+        }
+
+        let link = env.get("DEPLOYMENT_LINK");
+        let revisions = env.get("DEPLOYMENT_REVISIONS");
+        let generations = env.parse::<usize>("DEPLOYMENT_GENERATIONS")?;
+        if link.is_some() || revisions.is_some() || generations.is_some() {
+            let deployment = self.deployment.get_or_insert_with(Default::default);
+            if let Some(link) = link {
+                deployment.link = PathBuf::from(link);
+            }
+            if let Some(revisions) = revisions {
+                deployment.revisions = PathBuf::from(revisions);
+            }
+            if let Some(generations) = generations {
+                deployment.generations = generations;
+            }
+
+            if deployment.link.as_os_str().is_empty() || deployment.revisions.as_os_str().is_empty()
+            {
+                return Err(env.invalid(
+                    "DEPLOYMENT_LINK",
+                    format!(
+                        "deployments need both {ENV_PREFIX}DEPLOYMENT_LINK and {ENV_PREFIX}DEPLOYMENT_REVISIONS"
+                    ),
+                ));
+            }
+        }
+
+        let allowed_signers = env.get("SIGNATURES_ALLOWED_SIGNERS");
+        let keyring = env.get("SIGNATURES_KEYRING");
+        let verify_all_commits = env.bool("SIGNATURES_VERIFY_ALL_COMMITS")?;
+        if allowed_signers.is_some() || keyring.is_some() || verify_all_commits.is_some() {
+            let policy = self
+                .signature_policy
+                .get_or_insert_with(SignaturePolicy::default);
+            if let Some(allowed_signers) = allowed_signers {
+                policy.allowed_signers = Some(PathBuf::from(allowed_signers));
+            }
+            if let Some(keyring) = keyring {
+                policy.keyring = Some(PathBuf::from(keyring));
+            }
+            if let Some(verify_all_commits) = verify_all_commits {
+                policy.verify_all_commits = verify_all_commits;
+            }
+        }
+
+        if let Some(submodules) = env.bool("SUBMODULES")? {
+            self.submodules = submodules;
+        }
+        #[cfg(feature = "lfs")]
+        if let Some(lfs) = env.bool("LFS")? {
+            self.lfs = lfs;
+        }
+
+        // A misspelt name would otherwise be left out without a word
+        if let Some(name) = env.unread().next() {
+            return Err(invalid(name, "it isn't a setting gitsync reads"));
+        }
+
+        builder::validate(self)
+    }
+}
+
+/// The `GITSYNC_*` environment variables, looked up without their prefix.
+struct Env {
+    vars: BTreeMap<String, String>,
+    /// The names that were looked up, whether they were set or not.
+    read: RefCell<BTreeSet<String>>,
+}
+
+impl Env {
+    fn get(&self, key: &str) -> Option<&str> {
+        let name = format!("{ENV_PREFIX}{key}");
+        let value = self.vars.get(&name).map(String::as_str);
+        self.read.borrow_mut().insert(name);
+        value
+    }
+
+    /// The variables that are set but were never looked up.
+    fn unread(&self) -> impl Iterator<Item = &str> {
+        let read = self.read.borrow().clone();
+        self.vars
+            .keys()
+            .filter(move |name| !read.contains(*name))
+            .map(String::as_str)
+    }
+
+    fn parse<T>(&self, key: &str) -> Result<Option<T>, ConfigurationError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.get(key)
+            .map(|value| {
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|error| self.invalid(key, error))
+            })
+            .transpose()
+    }
+
+    fn bool(&self, key: &str) -> Result<Option<bool>, ConfigurationError> {
+        self.get(key)
+            .map(|value| match value.trim().to_ascii_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Ok(true),
+                "false" | "no" | "off" | "0" | "" => Ok(false),
+                _ => Err(self.invalid(key, "expected true or false")),
+            })
+            .transpose()
+    }
+
+    /// A list with one value per line.
+    fn lines(&self, key: &str) -> Option<Vec<String>> {
+        self.get(key).map(|value| {
+            value
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+    }
+
+    /// A comma-separated list.
+    fn list(&self, key: &str) -> Option<Vec<String>> {
+        self.get(key).map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+    }
+
+    /// A secret, given either inline or as the path to a file holding it.
+    fn secret(&self, key: &str) -> Result<Option<String>, ConfigurationError> {
+        let file_key = format!("{key}_FILE");
+
+        match (self.get(key), self.get(&file_key)) {
+            (Some(_), Some(_)) => Err(self.conflict(key, &file_key)),
+            (Some(value), None) => Ok(Some(value.to_owned())),
+            (None, Some(path)) => std::fs::read_to_string(path)
+                .map(|contents| Some(secret::from_file_contents(&contents).to_owned()))
+                .map_err(|error| ConfigurationError::UnreadableSecretFile {
+                    name: format!("{ENV_PREFIX}{file_key}"),
+                    path: PathBuf::from(path),
+                    reason: error.to_string(),
+                }),
+            (None, None) => Ok(None),
+        }
+    }
+
+    fn invalid(&self, key: &str, reason: impl Display) -> ConfigurationError {
+        invalid(&format!("{ENV_PREFIX}{key}"), reason)
+    }
+
+    fn conflict(&self, key: &str, other: &str) -> ConfigurationError {
+        self.invalid(key, format!("{ENV_PREFIX}{other} is set as well"))
+    }
+}
+
+fn invalid(name: &str, reason: impl Display) -> ConfigurationError {
+    ConfigurationError::InvalidEnvironmentVariable {
+        name: name.to_owned(),
+        reason: reason.to_string(),
+    }
+}
+
+fn parse_revision(revision: &str) -> Result<Oid, String> {
+    Oid::from_hex(revision.trim().as_bytes()).map_err(|error| error.to_string())
+}
+
+/// The time `seconds` after the Unix epoch, as `GITSYNC_SHALLOW_SINCE` and
+/// `Shallow::Since` are given.
+fn since(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+/// Deserialise `Shallow::Since` from Unix seconds, as `GITSYNC_SHALLOW_SINCE`
+/// takes it, rather than from the struct `SystemTime` would expect.
+#[cfg(feature = "serde")]
+pub(crate) fn deserialize_since<'de, D>(deserializer: D) -> Result<SystemTime, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::Deserialize;
+
+    u64::deserialize(deserializer).map(since)
+}
+
+/// Deserialise `GitSync::revision` from its hex form, rather than from the
+/// bytes `Oid` would expect.
+#[cfg(feature = "serde")]
+pub(crate) fn deserialize_revision<'de, D>(deserializer: D) -> Result<Option<Oid>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::Deserialize;
+
+    Option::<String>::deserialize(deserializer)?
+        .map(|revision| parse_revision(&revision).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMIT: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn environment_variables_override_the_configuration() {
+        let gitsync = GitSync {
+            repo: "https://example.com/org/app.git".to_owned(),
+            branch: Some("main".to_owned()),
+            ..Default::default()
+        }
+        .merge_env(vars(&[
+            ("GITSYNC_DIR", "/srv/app"),
+            ("GITSYNC_BRANCH", "release"),
+            ("GITSYNC_SPARSE_CHECKOUT", "deploy/prod, charts"),
+            ("GITSYNC_TAG_VERSION", ">=1.2, <2"),
+            ("GITSYNC_REVISION", COMMIT),
+            ("GITSYNC_DIVERGENCE", "hard_reset"),
+            ("GITSYNC_DIRTY_WORKTREE", "backup:/srv/backups"),
+            ("GITSYNC_SHALLOW_DEPTH", "1"),
+            ("GITSYNC_SUBMODULES", "true"),
+            (
+                "GITSYNC_CREDENTIAL_HELPER_PROGRAMS",
+                "store --file=/run/secrets/git-credentials\n/usr/local/bin/vault-helper",
+            ),
+            ("OTHER_DIR", "/srv/other"),
+        ]))
+        .unwrap();
+
+        assert_eq!(gitsync.repo, "https://example.com/org/app.git");
+        assert_eq!(gitsync.dir, PathBuf::from("/srv/app"));
+        assert_eq!(gitsync.branch.as_deref(), Some("release"));
+        assert_eq!(gitsync.sparse_checkout, vec!["deploy/prod", "charts"]);
+        assert_eq!(
+            gitsync.tag,
+            Some(TagSelector::SemVer(">=1.2, <2".parse().unwrap()))
+        );
+        assert_eq!(gitsync.revision.unwrap().to_string(), COMMIT);
+        assert_eq!(gitsync.divergence, DivergenceStrategy::HardReset);
+        assert_eq!(
+            gitsync.dirty_worktree,
+            DirtyWorktreePolicy::Backup(PathBuf::from("/srv/backups"))
+        );
+        assert_eq!(
+            gitsync.shallow,
+            Some(Shallow::Depth(NonZeroU32::new(1).unwrap()))
+        );
+        assert!(gitsync.submodules);
+        assert_eq!(
+            gitsync.credential_helpers,
+            Some(CredentialHelpers {
+                configured: true,
+                programs: vec![
+                    "store --file=/run/secrets/git-credentials".to_owned(),
+                    "/usr/local/bin/vault-helper".to_owned()
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn secrets_are_read_from_files() {
+        let token = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(token.path(), "secret-token\n").unwrap();
+
+        let gitsync = GitSync::default()
+            .merge_env(vars(&[
+                ("GITSYNC_REPO", "https://example.com/org/app.git"),
+                ("GITSYNC_DIR", "/srv/app"),
+                ("GITSYNC_TOKEN_FILE", token.path().to_str().unwrap()),
+            ]))
+            .unwrap();
+
+        assert_eq!(gitsync.token.unwrap().expose(), "secret-token");
+    }
+
+    #[test]
+    fn invalid_environment_variables_are_reported() {
+        let error = GitSync::default()
+            .merge_env(vars(&[
+                ("GITSYNC_REPO", "https://example.com/org/app.git"),
+                ("GITSYNC_DIR", "/srv/app"),
+                ("GITSYNC_DIVERGENCE", "sideways"),
+            ]))
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            ConfigurationError::InvalidEnvironmentVariable { ref name, .. } if name == "GITSYNC_DIVERGENCE"
+        ));
+
+        let error = GitSync::default()
+            .merge_env(vars(&[
+                ("GITSYNC_TOKEN", "secret-token"),
+                ("GITSYNC_TOKEN_FILE", "/run/secrets/token"),
+            ]))
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            ConfigurationError::InvalidEnvironmentVariable { ref name, .. } if name == "GITSYNC_TOKEN"
+        ));
+
+        let error = GitSync::default()
+            .merge_env(vars(&[(
+                "GITSYNC_PASSWORD_FILE",
+                "/nonexistent/gitsync/password",
+            )]))
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            ConfigurationError::UnreadableSecretFile { ref name, .. } if name == "GITSYNC_PASSWORD_FILE"
+        ));
+    }
+
+    #[test]
+    fn unknown_environment_variables_are_turned_down() {
+        let error = GitSync::default()
+            .merge_env(vars(&[
+                ("GITSYNC_REPO", "https://example.com/org/app.git"),
+                ("GITSYNC_DIR", "/srv/app"),
+                ("GITSYNC_BRANHC", "release"),
+            ]))
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            ConfigurationError::InvalidEnvironmentVariable { ref name, .. } if name == "GITSYNC_BRANHC"
+        ));
+    }
+
+    #[test]
+    fn the_merged_configuration_is_validated() {
+        assert_eq!(
+            ConfigurationError::MissingRepository,
+            GitSync::default()
+                .merge_env(vars(&[("GITSYNC_DIR", "/srv/app")]))
+                .unwrap_err()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn configuration_is_deserialised() {
+        let gitsync: GitSync = serde_json::from_str(&format!(
+            r#"{{
+                "repo": "https://example.com/org/app.git",
+                "dir": "/srv/app",
+                "tag": {{ "semver": ">=1.2" }},
+                "revision": "{COMMIT}",
+                "divergence": "rebase",
+                "dirty_worktree": "stash",
+                "shallow": {{ "since": 1700000000 }},
+                "deployment": {{ "link": "/srv/current", "revisions": "/srv/revisions" }}
+            }}"#
+        ))
+        .unwrap();
+
+        assert_eq!(gitsync.dir, PathBuf::from("/srv/app"));
+        assert_eq!(
+            gitsync.tag,
+            Some(TagSelector::SemVer(">=1.2".parse().unwrap()))
+        );
+        assert_eq!(gitsync.revision.unwrap().to_string(), COMMIT);
+        assert_eq!(gitsync.divergence, DivergenceStrategy::Rebase);
+        assert_eq!(gitsync.dirty_worktree, DirtyWorktreePolicy::Stash);
+        assert_eq!(
+            gitsync.shallow,
+            Some(Shallow::Since(
+                UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+            ))
+        );
+        assert_eq!(gitsync.deployment.unwrap().generations, 0);
+        assert!(serde_json::from_str::<GitSync>(r#"{ "repository": "typo" }"#).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use crate::errors::GitSyncError;
+use crate::secret;
+use crate::Secret;
+use gix::credentials::helper::{Action, Cascade};
+use gix::credentials::{protocol, Program};
+use gix::Repository;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Hands out the credentials of HTTP(S) remotes and their LFS servers.
+///
+/// It's asked again on every attempt to authenticate, so that credentials
+/// which expire, such as short-lived tokens, can be replaced while a
+/// `Watcher` keeps syncing.
+pub trait CredentialProvider: fmt::Debug + Send + Sync {
+    /// The credentials to authenticate to `url` with, or `None` if there
+    /// aren't any.
+    fn credentials(&self, url: &str) -> io::Result<Option<Credentials>>;
+}
+
+/// A password or token, along with the username to send it with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Credentials {
+    /// Falls back to `GitSync::username`, then to the username in the
+    /// remote's URL, then to `git`.
+    pub username: Option<String>,
+    pub password: Secret,
+}
+
+/// Always hands out the same credentials, as `GitSync::password` and
+/// `GitSync::token` do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaticCredentials {
+    pub credentials: Credentials,
+}
+
+impl CredentialProvider for StaticCredentials {
+    fn credentials(&self, _url: &str) -> io::Result<Option<Credentials>> {
+        Ok(Some(self.credentials.clone()))
+    }
+}
+
+/// Reads the password or token from a file every time it's needed, so that
+/// a mounted secret which is rotated in place (e.g. a Kubernetes secret
+/// volume) is picked up without restarting.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileCredentials {
+    pub username: Option<String>,
+    pub password_file: PathBuf,
+}
+
+impl CredentialProvider for FileCredentials {
+    fn credentials(&self, _url: &str) -> io::Result<Option<Credentials>> {
+        let contents = std::fs::read_to_string(&self.password_file)?;
+        let password = secret::from_file_contents(&contents);
+        if password.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Credentials {
+            username: self.username.clone(),
+            password: Secret::from(password),
+        }))
+    }
+}
+
+/// Git credential helpers to ask for the credentials of HTTP(S) remotes, as
+/// git does, in place of `GitSync::password` or `GitSync::token`.
+///
+/// The helpers are told whether the credentials they gave worked, so that
+/// they can store or erase them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct CredentialHelpers {
+    /// Ask the helpers set by `credential.helper` in the repository's, the
+    /// user's and the system's Git configuration first.
+    pub configured: bool,
+    /// More helpers to ask, in the form `credential.helper` takes: the name of
+    /// a `git credential-<name>` helper along with its arguments (e.g.
+    /// `store --file=/run/secrets/git-credentials`), the absolute path to a
+    /// program, or a shell snippet starting with `!`.
+    pub programs: Vec<String>,
+}
+
+impl Default for CredentialHelpers {
+    fn default() -> Self {
+        CredentialHelpers {
+            configured: true,
+            programs: Vec::new(),
+        }
+    }
+}
+
+impl CredentialHelpers {
+    /// The helpers to ask for the credentials of `url`, which is a remote of
+    /// `repository` (or its LFS server).
+    pub(crate) fn for_url(
+        &self,
+        repository: &Repository,
+        url: gix::Url,
+    ) -> Result<Helpers, GitSyncError> {
+        let (mut cascade, mut prompt) = if self.configured {
+            let (cascade, _, prompt) = repository
+                .config_snapshot()
+                .credential_helpers(url)
+                .map_err(GitSyncError::from_gix)?;
+            (cascade, prompt)
+        } else {
+            (Cascade::default(), Default::default())
+        };
+
+        cascade.programs.extend(
+            self.programs
+                .iter()
+                .map(|program| Program::from_custom_definition(program.as_str())),
+        );
+        // Nobody is at a terminal to answer
+        prompt.mode = gix::prompt::Mode::Disable;
+
+        Ok(Helpers { cascade, prompt })
+    }
+}
+
+/// The helpers that apply to a URL, ready to be asked.
+pub(crate) struct Helpers {
+    cascade: Cascade,
+    prompt: gix::prompt::Options<'static>,
+}
+
+impl Helpers {
+    /// Get, store or erase credentials, as the transport asks.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn invoke(&mut self, action: Action) -> protocol::Result {
+        self.cascade.invoke(action, self.prompt.clone())
+    }
+
+    /// Get the credentials for `url`, along with the action that stores or
+    /// erases them once they are known to work or not.
+    #[cfg(any(feature = "lfs", test))]
+    pub(crate) fn get(
+        &mut self,
+        url: &str,
+    ) -> Result<
+        (
+            gix::sec::identity::Account,
+            gix::credentials::helper::NextAction,
+        ),
+        GitSyncError,
+    > {
+        match self
+            .invoke(Action::get_for_url(url))
+            .map_err(GitSyncError::from_gix)?
+        {
+            Some(outcome) => Ok((outcome.identity, outcome.next)),
+            None => Err(GitSyncError::from_gix(protocol::Error::IdentityMissing {
+                context: Default::default(),
+            })),
+        }
+    }
+
+    /// Tell the helpers whether the credentials `next` came with worked.
+    #[cfg(any(feature = "lfs", test))]
+    pub(crate) fn report(
+        &mut self,
+        next: gix::credentials::helper::NextAction,
+        worked: bool,
+    ) -> Result<(), GitSyncError> {
+        let action = if worked { next.store() } else { next.erase() };
+        self.invoke(action).map_err(GitSyncError::from_gix)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn file_credentials_are_read_again_every_time() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let provider = FileCredentials {
+            username: Some("octocat".to_owned()),
+            password_file: file.path().to_owned(),
+        };
+        let url = "https://example.com/org/app.git";
+
+        assert_eq!(None, provider.credentials(url).unwrap());
+
+        writeln!(file, "first-token").unwrap();
+        let credentials = provider.credentials(url).unwrap().unwrap();
+        assert_eq!(credentials.username.as_deref(), Some("octocat"));
+        assert_eq!(credentials.password.expose(), "first-token");
+
+        std::fs::write(file.path(), "second-token\n").unwrap();
+        let credentials = provider.credentials(url).unwrap().unwrap();
+        assert_eq!(credentials.password.expose(), "second-token");
+    }
+
+    #[test]
+    fn missing_credential_files_are_an_error() {
+        let provider = FileCredentials {
+            username: None,
+            password_file: PathBuf::from("/nonexistent/token"),
+        };
+
+        assert!(provider
+            .credentials("https://example.com/org/app.git")
+            .is_err());
+    }
+
+    fn helpers(programs: &[String]) -> Helpers {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = gix::init(dir.path()).unwrap();
+
+        CredentialHelpers {
+            configured: false,
+            programs: programs.to_vec(),
+        }
+        .for_url(
+            &repository,
+            gix::url::parse("https://example.com/org/app.git".into()).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn custom_helpers_provide_credentials() {
+        let mut helpers =
+            helpers(&["!f() { echo username=octocat; echo password=secret-token; }; f".to_owned()]);
+
+        let (account, _) = helpers.get("https://example.com/org/app.git").unwrap();
+
+        assert_eq!(account.username, "octocat");
+        assert_eq!(account.password, "secret-token");
+    }
+
+    #[test]
+    fn helpers_are_told_whether_credentials_worked() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        let mut helpers = helpers(&[format!(
+            "!f() {{ echo $1 >> '{}'; test $1 != get || {{ echo username=octocat; echo password=secret-token; }}; }}; f",
+            log.display()
+        )]);
+
+        let (_, next) = helpers.get("https://example.com/org/app.git").unwrap();
+        helpers.report(next.clone(), true).unwrap();
+        helpers.report(next, false).unwrap();
+
+        assert_eq!(
+            "get\nstore\nerase\n",
+            std::fs::read_to_string(&log).unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_credentials_are_an_error() {
+        let mut helpers = helpers(&["!true".to_owned()]);
+
+        assert!(helpers.get("https://example.com/org/app.git").is_err());
+    }
+}
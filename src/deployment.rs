@@ -0,0 +1,256 @@
+use crate::errors::GitSyncError;
+use crate::{sparse, Oid};
+use gix::Repository;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+/// Deploy every synced commit into a directory of its own, then point a
+/// symlink at it, so that anything reading through the symlink never sees a
+/// half-updated tree.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct Deployment {
+    /// The symlink to the deployed revision, which is replaced atomically.
+    pub link: PathBuf,
+    /// Where each revision is written, to a directory named after its commit.
+    /// It should be outside of the clone.
+    pub revisions: PathBuf,
+    /// How many of the previously deployed revisions to keep. Older ones are
+    /// removed, going by the order they were deployed in, which is recorded
+    /// in `revisions`.
+    pub generations: usize,
+}
+
+impl Deployment {
+    /// Write out `commit` (unless it was deployed before) and swap `link`
+    /// over to it, returning the revision's directory.
+    pub(crate) fn deploy(
+        &self,
+        repository: &Repository,
+        commit: Oid,
+    ) -> Result<PathBuf, GitSyncError> {
+        std::fs::create_dir_all(&self.revisions)
+            .map_err(|error| GitSyncError::GenericError { error })?;
+        // The link has to keep working from wherever it is read
+        let revisions = self
+            .revisions
+            .canonicalize()
+            .map_err(|error| GitSyncError::GenericError { error })?;
+
+        let revision = revisions.join(commit.to_string());
+        if !revision.is_dir() {
+            write_revision(repository, commit, &revisions, &revision)?;
+        }
+
+        if std::fs::read_link(&self.link).ok().as_ref() != Some(&revision) {
+            swap_link(&self.link, &revision)?;
+        }
+
+        self.remove_old_revisions(&revisions, commit)?;
+
+        Ok(revision)
+    }
+
+    /// Record `current` as the newest deployment and remove all but the
+    /// newest `generations` revisions besides it.
+    fn remove_old_revisions(&self, revisions: &Path, current: Oid) -> Result<(), GitSyncError> {
+        let manifest = revisions.join(MANIFEST);
+        let mut deployed = read_manifest(&manifest)?;
+
+        // Revisions that aren't in the manifest were deployed before it was
+        // kept, so they count as the oldest
+        let mut unrecorded = Vec::new();
+        for entry in
+            std::fs::read_dir(revisions).map_err(|error| GitSyncError::GenericError { error })?
+        {
+            let entry = entry.map_err(|error| GitSyncError::GenericError { error })?;
+            let commit = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| Oid::from_hex(name.as_bytes()).ok());
+
+            if let Some(commit) = commit.filter(|commit| !deployed.contains(commit)) {
+                unrecorded.push(commit);
+            }
+        }
+        unrecorded.sort();
+        deployed.splice(0..0, unrecorded);
+
+        // Redeploying makes it the newest revision again
+        deployed.retain(|commit| *commit != current);
+        deployed.push(current);
+
+        let removed = deployed.len().saturating_sub(self.generations + 1);
+        for commit in deployed.drain(..removed) {
+            std::fs::remove_dir_all(revisions.join(commit.to_string()))
+                .map_err(|error| GitSyncError::GenericError { error })?;
+        }
+
+        write_manifest(&manifest, &deployed)
+    }
+}
+
+/// The file in `Deployment::revisions` that lists the deployed commits, oldest
+/// first.
+const MANIFEST: &str = ".deployed";
+
+fn read_manifest(manifest: &Path) -> Result<Vec<Oid>, GitSyncError> {
+    let contents = match std::fs::read_to_string(manifest) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(GitSyncError::GenericError { error }),
+    };
+
+    // Anything that isn't a commit can't have been written by `write_manifest`
+    Ok(contents
+        .lines()
+        .filter_map(|line| Oid::from_hex(line.trim().as_bytes()).ok())
+        .collect())
+}
+
+/// Replace `manifest` in one go, so that it is never left half-written.
+fn write_manifest(manifest: &Path, deployed: &[Oid]) -> Result<(), GitSyncError> {
+    let contents: String = deployed
+        .iter()
+        .map(|commit| format!("{commit}\n"))
+        .collect();
+    let temp_manifest = manifest.with_file_name(format!("{MANIFEST}.{}", fastrand::u32(..)));
+
+    std::fs::write(&temp_manifest, contents)
+        .and_then(|_| std::fs::rename(&temp_manifest, manifest))
+        .map_err(|error| {
+            let _ = std::fs::remove_file(&temp_manifest);
+            GitSyncError::GenericError { error }
+        })
+}
+
+/// Check out `commit`'s tree into `revision`, keeping to the repository's
+/// sparse checkout. It is written to a temporary directory first, so that
+/// `revision` only ever exists complete.
+fn write_revision(
+    repository: &Repository,
+    commit: Oid,
+    revisions: &Path,
+    revision: &Path,
+) -> Result<(), GitSyncError> {
+    let tree_id = repository
+        .find_commit(commit)
+        .map_err(GitSyncError::from_gix)?
+        .tree_id()
+        .map_err(GitSyncError::from_gix)?;
+    let mut index = repository
+        .index_from_tree(&tree_id)
+        .map_err(GitSyncError::from_gix)?;
+
+    let directories = sparse::directories(repository)?;
+    if !directories.is_empty() {
+        sparse::skip_outside_of_cone(&mut index, &directories);
+    }
+
+    let temp_dir = tempfile::Builder::new()
+        .prefix(&format!(".{commit}-"))
+        .tempdir_in(revisions)
+        .map_err(|error| GitSyncError::GenericError { error })?;
+
+    let mut options = repository
+        .checkout_options(gix::worktree::stack::state::attributes::Source::IdMapping)
+        .map_err(GitSyncError::from_gix)?;
+    options.destination_is_initially_empty = true;
+
+    gix::worktree::state::checkout(
+        &mut index,
+        temp_dir.path(),
+        repository
+            .objects
+            .clone()
+            .into_arc()
+            .map_err(GitSyncError::from_gix)?,
+        &gix::progress::Discard,
+        &gix::progress::Discard,
+        &AtomicBool::new(false),
+        options,
+    )
+    .map_err(GitSyncError::from_gix)?;
+    // Only objects that were downloaded along with the clone's worktree
+    #[cfg(feature = "lfs")]
+    crate::lfs::smudge(repository, &index, temp_dir.path())?;
+
+    std::fs::rename(temp_dir.keep(), revision).map_err(|error| GitSyncError::GenericError { error })
+}
+
+/// Point `link` at `target` by renaming a new symlink over it, which readers
+/// can't observe halfway.
+fn swap_link(link: &Path, target: &Path) -> Result<(), GitSyncError> {
+    if let Some(parent) = link
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent).map_err(|error| GitSyncError::GenericError { error })?;
+    }
+
+    let name = link
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temp_link = link.with_file_name(format!(".{name}.{}", fastrand::u32(..)));
+
+    symlink_dir(target, &temp_link)
+        .and_then(|_| std::fs::rename(&temp_link, link))
+        .map_err(|error| {
+            let _ = std::fs::remove_file(&temp_link);
+            GitSyncError::GenericError { error }
+        })
+}
+
+#[cfg(unix)]
+fn symlink_dir(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink_dir(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn revisions_are_removed_in_the_order_they_were_deployed() {
+        let revisions = tempfile::tempdir().unwrap();
+        let deployment = Deployment {
+            generations: 1,
+            ..Default::default()
+        };
+        let deploy = |commit: Oid| {
+            std::fs::create_dir_all(revisions.path().join(commit.to_string())).unwrap();
+            deployment
+                .remove_old_revisions(revisions.path(), commit)
+                .unwrap();
+        };
+        let deployed = || {
+            let mut deployed: Vec<_> = std::fs::read_dir(revisions.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .filter(|name| !name.starts_with('.'))
+                .collect();
+            deployed.sort();
+            deployed
+        };
+        let [first, second, third] = [1, 2, 3].map(|byte| Oid::Sha1([byte; 20]));
+
+        deploy(first);
+        deploy(second);
+        // Going back to the first makes the second the older one
+        deploy(first);
+        deploy(third);
+
+        assert_eq!(vec![first.to_string(), third.to_string()], deployed());
+        assert_eq!(
+            vec![first, third],
+            read_manifest(&revisions.path().join(MANIFEST)).unwrap()
+        );
+    }
+}
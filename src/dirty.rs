@@ -0,0 +1,366 @@
+use crate::errors::GitSyncError;
+use crate::Oid;
+use gix::bstr::{BStr, BString, ByteSlice};
+use gix::objs::tree::EntryKind;
+use gix::refs::transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog};
+use gix::Repository;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The reference whose reflog holds every snapshot taken by
+/// `DirtyWorktreePolicy::Stash`.
+pub const STASH_REFERENCE: &str = "refs/gitsync/stash";
+
+/// What `sync` does when the worktree has local changes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DirtyWorktreePolicy {
+    /// Fail with `GitSyncError::WorkTreeNotClean`.
+    #[default]
+    Fail,
+    /// Discard the changes, including untracked files.
+    Discard,
+    /// Commit a snapshot of the worktree to `STASH_REFERENCE` before
+    /// discarding the changes. Earlier snapshots are kept in its reflog.
+    Stash,
+    /// Copy the changed and untracked files into a new directory inside this
+    /// one before discarding the changes.
+    Backup(PathBuf),
+}
+
+/// At most this many paths are listed in `GitSyncError::WorkTreeNotClean`.
+const MAX_REPORTED_PATHS: usize = 20;
+
+/// How a path in the worktree differs from `HEAD`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DirtyKind {
+    Modified,
+    Deleted,
+    Untracked,
+}
+
+pub(crate) type DirtyPaths = BTreeMap<BString, DirtyKind>;
+
+/// The paths that differ between `HEAD` and the worktree, whether the change
+/// was added to the index or not.
+pub(crate) fn dirty_paths(repository: &Repository) -> Result<DirtyPaths, GitSyncError> {
+    let statuses = repository
+        .status(gix::progress::Discard)
+        .map_err(GitSyncError::from_gix)?
+        .untracked_files(gix::status::UntrackedFiles::Files)
+        .into_iter(Vec::<BString>::new())
+        .map_err(GitSyncError::from_gix)?;
+
+    let mut paths = DirtyPaths::new();
+    for status in statuses {
+        match status.map_err(GitSyncError::from_gix)? {
+            // The worktree has the final say on how a path differs
+            gix::status::Item::IndexWorktree(item) => {
+                use gix::status::index_worktree::Item;
+                use gix::status::plumbing::index_as_worktree::{Change, EntryStatus};
+
+                let kind = match &item {
+                    Item::Modification {
+                        status: EntryStatus::Change(Change::Removed),
+                        ..
+                    } => DirtyKind::Deleted,
+                    Item::DirectoryContents { .. } => DirtyKind::Untracked,
+                    Item::Rewrite { source, .. } => {
+                        paths.insert(source.rela_path().to_owned(), DirtyKind::Deleted);
+                        DirtyKind::Untracked
+                    }
+                    Item::Modification { .. } => DirtyKind::Modified,
+                };
+
+                // Files that were only touched, or are ignored, aren't changes
+                if item.summary().is_some() {
+                    paths.insert(item.rela_path().to_owned(), kind);
+                }
+            }
+            gix::status::Item::TreeIndex(change) => {
+                use gix::diff::index::ChangeRef;
+
+                let kind = match &change {
+                    ChangeRef::Deletion { .. } => DirtyKind::Deleted,
+                    ChangeRef::Rewrite {
+                        source_location, ..
+                    } => {
+                        paths
+                            .entry(source_location.clone().into_owned())
+                            .or_insert(DirtyKind::Deleted);
+                        DirtyKind::Modified
+                    }
+                    ChangeRef::Addition { .. } | ChangeRef::Modification { .. } => {
+                        DirtyKind::Modified
+                    }
+                };
+                paths.entry(change.location().to_owned()).or_insert(kind);
+            }
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Describe `paths` in a `GitSyncError::WorkTreeNotClean`, listing no more
+/// than `MAX_REPORTED_PATHS` of them.
+pub(crate) fn not_clean_error(paths: &DirtyPaths) -> GitSyncError {
+    let (mut modified, mut deleted, mut untracked) = (Vec::new(), Vec::new(), Vec::new());
+
+    for (path, kind) in paths.iter().take(MAX_REPORTED_PATHS) {
+        let path = gix::path::from_bstr(path.as_bstr()).into_owned();
+        match kind {
+            DirtyKind::Modified => modified.push(path),
+            DirtyKind::Deleted => deleted.push(path),
+            DirtyKind::Untracked => untracked.push(path),
+        }
+    }
+
+    GitSyncError::WorkTreeNotClean {
+        modified,
+        deleted,
+        untracked,
+        omitted: paths.len().saturating_sub(MAX_REPORTED_PATHS),
+    }
+}
+
+/// Commit the worktree's versions of `paths` on top of `HEAD`, and point
+/// `STASH_REFERENCE` at the commit.
+pub(crate) fn stash(repository: &Repository, paths: &DirtyPaths) -> Result<Oid, GitSyncError> {
+    let workdir = repository.workdir().expect("syncs always have a worktree");
+    let head = repository.head_commit().map_err(GitSyncError::from_gix)?;
+    let mut tree = repository
+        .edit_tree(head.tree_id().map_err(GitSyncError::from_gix)?)
+        .map_err(GitSyncError::from_gix)?;
+
+    for path in paths.keys() {
+        let file = workdir.join(gix::path::from_bstr(path.as_bstr()));
+        let metadata = match std::fs::symlink_metadata(&file) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                tree.remove(path.as_bstr())
+                    .map_err(GitSyncError::from_gix)?;
+                continue;
+            }
+            Err(error) => return Err(GitSyncError::GenericError { error }),
+        };
+
+        let (kind, contents) = if metadata.file_type().is_symlink() {
+            let target =
+                std::fs::read_link(&file).map_err(|error| GitSyncError::GenericError { error })?;
+            (
+                EntryKind::Link,
+                gix::path::into_bstr(target).into_owned().into(),
+            )
+        } else if metadata.is_file() {
+            let kind = if is_executable(&metadata) {
+                EntryKind::BlobExecutable
+            } else {
+                EntryKind::Blob
+            };
+            (
+                kind,
+                std::fs::read(&file).map_err(|error| GitSyncError::GenericError { error })?,
+            )
+        } else {
+            continue;
+        };
+
+        let blob = repository
+            .write_blob(contents)
+            .map_err(GitSyncError::from_gix)?;
+        tree.upsert(path.as_bstr(), kind, blob)
+            .map_err(GitSyncError::from_gix)?;
+    }
+
+    let tree = tree.write().map_err(GitSyncError::from_gix)?;
+    let committer = repository
+        .committer()
+        .expect("sync sets a fallback committer")
+        .map_err(GitSyncError::from_gix)?;
+    let commit = repository
+        .new_commit_as(
+            committer,
+            committer,
+            "Local changes discarded by sync\n",
+            tree,
+            [head.id],
+        )
+        .map_err(GitSyncError::from_gix)?;
+
+    repository
+        .edit_reference(RefEdit {
+            change: Change::Update {
+                log: LogChange {
+                    mode: RefLog::AndReference,
+                    force_create_reflog: true,
+                    message: "stash local changes before sync".into(),
+                },
+                expected: PreviousValue::Any,
+                new: gix::refs::Target::Object(commit.id),
+            },
+            name: STASH_REFERENCE
+                .try_into()
+                .expect("the stash reference name is valid"),
+            deref: false,
+        })
+        .map_err(GitSyncError::from_gix)?;
+
+    Ok(commit.id)
+}
+
+/// Copy the worktree's versions of `paths` into a new directory inside
+/// `directory`, returning the new directory.
+pub(crate) fn backup(
+    repository: &Repository,
+    paths: &DirtyPaths,
+    directory: &Path,
+) -> Result<PathBuf, GitSyncError> {
+    let workdir = repository.workdir().expect("syncs always have a worktree");
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    std::fs::create_dir_all(directory).map_err(|error| GitSyncError::GenericError { error })?;
+    let backup = tempfile::Builder::new()
+        .prefix(&format!("{seconds}-"))
+        .tempdir_in(directory)
+        .map_err(|error| GitSyncError::GenericError { error })?
+        .keep();
+
+    for path in paths.keys() {
+        let path = gix::path::from_bstr(path.as_bstr());
+        let source = workdir.join(&path);
+        let destination = backup.join(&path);
+
+        let metadata = match std::fs::symlink_metadata(&source) {
+            Ok(metadata) => metadata,
+            // Deleted files can be restored from `HEAD`
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(GitSyncError::GenericError { error }),
+        };
+
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|error| GitSyncError::GenericError { error })?;
+        }
+
+        if metadata.file_type().is_symlink() {
+            copy_symlink(&source, &destination)
+        } else if metadata.is_file() {
+            std::fs::copy(&source, &destination).map(|_| ())
+        } else {
+            continue;
+        }
+        .map_err(|error| GitSyncError::GenericError { error })?;
+    }
+
+    Ok(backup)
+}
+
+/// Delete whichever of `paths` are left in the worktree but aren't in `HEAD`,
+/// once the tracked files have been reset.
+pub(crate) fn remove_untracked(
+    repository: &Repository,
+    paths: &DirtyPaths,
+) -> Result<(), GitSyncError> {
+    let workdir = repository.workdir().expect("syncs always have a worktree");
+    let head_tree = repository
+        .head_commit()
+        .map_err(GitSyncError::from_gix)?
+        .tree()
+        .map_err(GitSyncError::from_gix)?;
+
+    for path in paths.keys() {
+        if is_in_tree(&head_tree, path.as_bstr())? {
+            continue;
+        }
+
+        crate::checkout::remove_file(workdir, path.as_bstr())?;
+    }
+
+    Ok(())
+}
+
+fn is_in_tree(tree: &gix::Tree<'_>, path: &BStr) -> Result<bool, GitSyncError> {
+    Ok(tree
+        .lookup_entry(path.split(|byte| *byte == b'/'))
+        .map_err(GitSyncError::from_gix)?
+        .is_some())
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn copy_symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(source)?, destination)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::fs::copy(source, destination).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_clean_error_lists_paths_by_kind() {
+        let paths = DirtyPaths::from([
+            (BString::from("deleted"), DirtyKind::Deleted),
+            (BString::from("dir/modified"), DirtyKind::Modified),
+            (BString::from("untracked"), DirtyKind::Untracked),
+        ]);
+
+        match not_clean_error(&paths) {
+            GitSyncError::WorkTreeNotClean {
+                modified,
+                deleted,
+                untracked,
+                omitted,
+            } => {
+                assert_eq!(vec![PathBuf::from("dir/modified")], modified);
+                assert_eq!(vec![PathBuf::from("deleted")], deleted);
+                assert_eq!(vec![PathBuf::from("untracked")], untracked);
+                assert_eq!(0, omitted);
+            }
+            error => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[test]
+    fn not_clean_error_caps_the_paths_listed() {
+        let paths: DirtyPaths = (0..MAX_REPORTED_PATHS + 5)
+            .map(|index| {
+                (
+                    BString::from(format!("file-{index:02}")),
+                    DirtyKind::Modified,
+                )
+            })
+            .collect();
+
+        match not_clean_error(&paths) {
+            GitSyncError::WorkTreeNotClean {
+                modified, omitted, ..
+            } => {
+                assert_eq!(MAX_REPORTED_PATHS, modified.len());
+                assert_eq!(5, omitted);
+            }
+            error => panic!("unexpected error {:?}", error),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::errors::GitSyncError;
+use crate::Oid;
+use gix::merge::tree::TreatAsUnresolved;
+use gix::Repository;
+
+/// What `sync` does when the branch can't be fast-forwarded to the remote,
+/// e.g. because the remote was force-pushed or there are local commits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DivergenceStrategy {
+    /// Fail with `GitSyncError::FastForwardMergeNotPossible`.
+    #[default]
+    Fail,
+    /// Reset the branch to the remote, dropping the commits only it had.
+    HardReset,
+    /// Replay the local commits on top of the remote, as `git rebase` would.
+    Rebase,
+    /// Merge the remote into the branch.
+    Merge,
+}
+
+impl DivergenceStrategy {
+    /// Work out the commit to move the branch to from `local`, which `remote`
+    /// doesn't descend from, along with whether that rewrites the branch's
+    /// history.
+    ///
+    /// `upstream` is where the remote branch was before fetching, which tells
+    /// the local commits apart from those dropped by a force-push.
+    pub(crate) fn resolve(
+        self,
+        repository: &Repository,
+        branch: &str,
+        local: Oid,
+        remote: Oid,
+        upstream: Option<Oid>,
+    ) -> Result<(Oid, bool), GitSyncError> {
+        let contains_remote = || -> Result<bool, GitSyncError> {
+            Ok(repository
+                .merge_base(local, remote)
+                .map_err(GitSyncError::from_gix)?
+                == remote)
+        };
+
+        match self {
+            DivergenceStrategy::Fail => Err(GitSyncError::FastForwardMergeNotPossible),
+            DivergenceStrategy::HardReset => Ok((remote, true)),
+            DivergenceStrategy::Rebase if contains_remote()? => Ok((local, false)),
+            DivergenceStrategy::Rebase => Ok((rebase(repository, local, remote, upstream)?, true)),
+            DivergenceStrategy::Merge if contains_remote()? => Ok((local, false)),
+            DivergenceStrategy::Merge => Ok((merge(repository, branch, local, remote)?, false)),
+        }
+    }
+
+    /// How the strategy is described in the reflog.
+    pub(crate) fn action(self) -> &'static str {
+        match self {
+            DivergenceStrategy::Fail => "fail",
+            DivergenceStrategy::HardReset => "hard-reset",
+            DivergenceStrategy::Rebase => "rebase",
+            DivergenceStrategy::Merge => "merge",
+        }
+    }
+}
+
+/// Replay the commits on `local` that neither `remote` nor `upstream` have on
+/// top of `remote`, leaving out merge commits.
+fn rebase(
+    repository: &Repository,
+    local: Oid,
+    remote: Oid,
+    upstream: Option<Oid>,
+) -> Result<Oid, GitSyncError> {
+    let mut commits = repository
+        .rev_walk([local])
+        .with_hidden(Some(remote).into_iter().chain(upstream))
+        .all()
+        .map_err(GitSyncError::from_gix)?
+        .filter_map(|info| match info {
+            Ok(info) if info.parent_ids.len() > 1 => None,
+            info => Some(info.map(|info| info.id)),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(GitSyncError::from_gix)?;
+    commits.reverse();
+
+    let committer = committer(repository)?;
+    let mut onto = remote;
+
+    for id in commits {
+        let commit = repository.find_commit(id).map_err(GitSyncError::from_gix)?;
+        let parent_tree = match commit.parent_ids().next() {
+            Some(parent) => tree_of(repository, parent.detach())?,
+            None => repository.empty_tree().id,
+        };
+
+        let mut merge = repository
+            .merge_trees(
+                parent_tree,
+                tree_of(repository, onto)?,
+                commit.tree_id().map_err(GitSyncError::from_gix)?,
+                Default::default(),
+                repository
+                    .tree_merge_options()
+                    .map_err(GitSyncError::from_gix)?,
+            )
+            .map_err(GitSyncError::from_gix)?;
+
+        if merge.has_unresolved_conflicts(TreatAsUnresolved::default()) {
+            return Err(GitSyncError::ConflictingChanges { commit: id, onto });
+        }
+
+        let tree = merge.tree.write().map_err(GitSyncError::from_gix)?;
+        let author = commit.author().map_err(GitSyncError::from_gix)?;
+        let message = commit.message_raw().map_err(GitSyncError::from_gix)?;
+        onto = repository
+            .new_commit_as(committer, author, message.to_string(), tree, [onto])
+            .map_err(GitSyncError::from_gix)?
+            .id;
+    }
+
+    Ok(onto)
+}
+
+/// Create a merge commit of `remote` into `local`.
+fn merge(
+    repository: &Repository,
+    branch: &str,
+    local: Oid,
+    remote: Oid,
+) -> Result<Oid, GitSyncError> {
+    let mut merge = repository
+        .merge_commits(
+            local,
+            remote,
+            Default::default(),
+            repository
+                .tree_merge_options()
+                .map_err(GitSyncError::from_gix)?
+                .into(),
+        )
+        .map_err(GitSyncError::from_gix)?;
+
+    if merge
+        .tree_merge
+        .has_unresolved_conflicts(TreatAsUnresolved::default())
+    {
+        return Err(GitSyncError::ConflictingChanges {
+            commit: local,
+            onto: remote,
+        });
+    }
+
+    let tree = merge
+        .tree_merge
+        .tree
+        .write()
+        .map_err(GitSyncError::from_gix)?;
+    let committer = committer(repository)?;
+
+    Ok(repository
+        .new_commit_as(
+            committer,
+            committer,
+            format!("Merge remote-tracking branch 'origin/{branch}'\n"),
+            tree,
+            [local, remote],
+        )
+        .map_err(GitSyncError::from_gix)?
+        .id)
+}
+
+fn committer(repository: &Repository) -> Result<gix::actor::SignatureRef<'_>, GitSyncError> {
+    repository
+        .committer()
+        .expect("sync sets a fallback committer")
+        .map_err(GitSyncError::from_gix)
+}
+
+fn tree_of(repository: &Repository, commit: Oid) -> Result<Oid, GitSyncError> {
+    Ok(repository
+        .find_commit(commit)
+        .map_err(GitSyncError::from_gix)?
+        .tree_id()
+        .map_err(GitSyncError::from_gix)?
+        .detach())
+}
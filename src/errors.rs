@@ -1,3 +1,6 @@
+use crate::secret::redact_credentials;
+use crate::Oid;
+use std::borrow::Cow;
 use std::{error::Error, fmt, path::PathBuf};
 
 #[derive(Debug)]
@@ -10,14 +13,52 @@ pub enum GitSyncError {
     CurrentBranchUnknown {
         dir: PathBuf,
     },
-    WorkTreeNotClean,
+    WorkTreeNotClean {
+        modified: Vec<PathBuf>,
+        deleted: Vec<PathBuf>,
+        untracked: Vec<PathBuf>,
+        /// How many more paths are dirty than are listed.
+        omitted: usize,
+    },
     FastForwardMergeNotPossible,
+    ConflictingChanges {
+        commit: Oid,
+        onto: Oid,
+    },
+    InsufficientHistory {
+        ancestor: Oid,
+        descendant: Oid,
+    },
+    NoMatchingTag {
+        selector: String,
+    },
+    RevisionNotFound {
+        revision: Oid,
+    },
+    RollbackNotPossible {
+        steps: usize,
+        /// How many earlier syncs there are to roll back to.
+        recorded: usize,
+    },
+    Cancelled,
+    LfsDownloadFailed {
+        reason: String,
+    },
+    UntrustedCommit {
+        commit: Oid,
+        reason: String,
+    },
+    InvalidConfiguration {
+        error: ConfigurationError,
+    },
     GixError {
         error: Box<dyn Error + Send + Sync>,
     },
-    GitCommandError {
-        command: String,
-        stderr: String,
+    InvalidPrivateKey {
+        error: Box<dyn Error + Send + Sync>,
+    },
+    HostKeyVerificationFailed {
+        url: String,
     },
     GenericError {
         error: std::io::Error,
@@ -53,16 +94,102 @@ impl fmt::Display for GitSyncError {
                 write!(f, "Can't fast-forward merge")
             }
 
-            GitSyncError::WorkTreeNotClean => {
-                write!(f, "The worktree isn't clean. Refusing to sync")
+            GitSyncError::ConflictingChanges { commit, onto } => {
+                write!(
+                    f,
+                    "Can't combine {commit} with {onto}, as their changes conflict"
+                )
+            }
+
+            GitSyncError::InsufficientHistory {
+                ancestor,
+                descendant,
+            } => {
+                write!(
+                    f,
+                    "Can't tell whether {descendant} is a fast-forward of {ancestor}, as the shallow clone's history doesn't go back far enough. Clone with more history to sync."
+                )
+            }
+
+            GitSyncError::NoMatchingTag { selector } => {
+                write!(f, "The remote has no tag matching {selector}")
+            }
+
+            GitSyncError::RevisionNotFound { revision } => {
+                write!(
+                    f,
+                    "The pinned revision {revision} wasn't fetched from the remote"
+                )
+            }
+
+            GitSyncError::RollbackNotPossible { steps, recorded } => {
+                write!(
+                    f,
+                    "Can't roll back {steps} sync(s), as only {recorded} earlier commit(s) are recorded"
+                )
+            }
+
+            GitSyncError::Cancelled => {
+                write!(f, "The operation was cancelled")
+            }
+
+            GitSyncError::LfsDownloadFailed { reason } => {
+                write!(f, "Git LFS objects couldn't be downloaded, as {reason}")
+            }
+
+            GitSyncError::UntrustedCommit { commit, reason } => {
+                write!(
+                    f,
+                    "Commit {commit} can't be trusted: {reason}. Refusing to check it out."
+                )
+            }
+
+            GitSyncError::WorkTreeNotClean {
+                modified,
+                deleted,
+                untracked,
+                omitted,
+            } => {
+                write!(f, "The worktree isn't clean. Refusing to sync")?;
+
+                for (kind, paths) in [
+                    ("modified", modified),
+                    ("deleted", deleted),
+                    ("untracked", untracked),
+                ] {
+                    if !paths.is_empty() {
+                        let paths: Vec<_> = paths
+                            .iter()
+                            .map(|path| path.display().to_string())
+                            .collect();
+                        write!(f, "\n  {kind}: {}", paths.join(", "))?;
+                    }
+                }
+
+                if *omitted > 0 {
+                    write!(f, "\n  and {omitted} more")?;
+                }
+
+                Ok(())
+            }
+
+            GitSyncError::InvalidConfiguration { error } => {
+                write!(f, "GitSync isn't configured correctly: {error}")
             }
 
             GitSyncError::GixError { error } => {
                 write!(f, "There was an error reported by gix: {error}")
             }
 
-            GitSyncError::GitCommandError { command, stderr } => {
-                write!(f, "Git command `{command}` failed: {stderr}")
+            GitSyncError::InvalidPrivateKey { error } => {
+                write!(f, "The SSH private key could not be used: {error}")
+            }
+
+            GitSyncError::HostKeyVerificationFailed { url } => {
+                write!(
+                    f,
+                    "The host key presented by {url} doesn't match any of the known hosts. Refusing to connect."
+                )
             }
 
             GitSyncError::GenericError { error } => {
@@ -75,9 +202,10 @@ impl fmt::Display for GitSyncError {
 impl Error for GitSyncError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
+            GitSyncError::InvalidConfiguration { error } => Some(error),
             GitSyncError::GixError { error } => Some(error.as_ref()),
+            GitSyncError::InvalidPrivateKey { error } => Some(error.as_ref()),
             GitSyncError::GenericError { error } => Some(error),
-            GitSyncError::GitCommandError { .. } => None,
             _ => None,
         }
     }
@@ -85,8 +213,115 @@ impl Error for GitSyncError {
 
 impl GitSyncError {
     pub fn from_gix(error: impl Error + Send + Sync + 'static) -> Self {
+        // gix names the remote's URL in its errors, credentials and all
+        let message = error.to_string();
+        if let Cow::Owned(message) = redact_credentials(&message) {
+            return GitSyncError::GixError {
+                error: message.into(),
+            };
+        }
+
         GitSyncError::GixError {
             error: Box::new(error),
         }
     }
 }
+
+/// Why `GitSyncBuilder::build` turned down a configuration.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConfigurationError {
+    MissingRepository,
+    InvalidRepository {
+        repo: String,
+        reason: String,
+    },
+    MissingDirectory,
+    /// The directory's path is taken by something other than a directory.
+    InvalidDirectory {
+        dir: PathBuf,
+    },
+    InvalidBranch {
+        branch: String,
+        reason: String,
+    },
+    /// Both credentials are set, but only one of them can be used.
+    ConflictingCredentials {
+        first: &'static str,
+        second: &'static str,
+    },
+    /// `credential` is set, but is of no use without `requires`.
+    MissingCredential {
+        credential: &'static str,
+        requires: &'static str,
+    },
+    InvalidEnvironmentVariable {
+        name: String,
+        reason: String,
+    },
+    /// The file named by the `name` environment variable couldn't be read.
+    UnreadableSecretFile {
+        name: String,
+        path: PathBuf,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ConfigurationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigurationError::MissingRepository => {
+                write!(f, "No repository is set to clone from")
+            }
+
+            ConfigurationError::InvalidRepository { repo, reason } => {
+                write!(f, "The repository {repo} isn't a valid Git URL: {reason}")
+            }
+
+            ConfigurationError::MissingDirectory => {
+                write!(f, "No directory is set to clone into")
+            }
+
+            ConfigurationError::InvalidDirectory { dir } => {
+                write!(f, "{} already exists, but isn't a directory", dir.display())
+            }
+
+            ConfigurationError::InvalidBranch { branch, reason } => {
+                write!(f, "{branch} isn't a valid branch name: {reason}")
+            }
+
+            ConfigurationError::ConflictingCredentials { first, second } => {
+                write!(f, "Only one of {first} and {second} can be set")
+            }
+
+            ConfigurationError::MissingCredential {
+                credential,
+                requires,
+            } => {
+                write!(
+                    f,
+                    "{credential} is set, but can't be used without {requires}"
+                )
+            }
+
+            ConfigurationError::InvalidEnvironmentVariable { name, reason } => {
+                write!(f, "The environment variable {name} is invalid: {reason}")
+            }
+
+            ConfigurationError::UnreadableSecretFile { name, path, reason } => {
+                write!(
+                    f,
+                    "The file {} named by {name} couldn't be read: {reason}",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl Error for ConfigurationError {}
+
+impl From<ConfigurationError> for GitSyncError {
+    fn from(error: ConfigurationError) -> Self {
+        GitSyncError::InvalidConfiguration { error }
+    }
+}
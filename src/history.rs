@@ -0,0 +1,247 @@
+use crate::errors::GitSyncError;
+use crate::Oid;
+use gix::bstr::ByteSlice;
+use gix::refs::transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog};
+use gix::Repository;
+use std::convert::{TryFrom, TryInto};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The reference whose reflog records every commit checked out by
+/// `bootstrap`, `sync` and `rollback`.
+pub const HISTORY_REFERENCE: &str = "refs/gitsync/history";
+
+/// The reference left by `GitSync::rollback`, pointing at the commit that
+/// was rolled back from. Syncs stay on the rolled back commit for as long as
+/// it exists.
+pub const ROLLBACK_REFERENCE: &str = "refs/gitsync/rollback";
+
+/// A commit that was checked out, as recorded in the reflog of
+/// `HISTORY_REFERENCE`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyncRecord {
+    pub commit: Oid,
+    /// The commit checked out before, unless this is the first record.
+    pub previous: Option<Oid>,
+    pub time: SystemTime,
+    /// Whether the commit was checked out by `GitSync::rollback`.
+    pub rollback: bool,
+}
+
+/// Record that `current` was checked out, by `action`, in place of `previous`.
+///
+/// The first record also notes `previous`, so that a clone made by something
+/// other than `bootstrap` can be rolled back to the commit it started out at.
+pub(crate) fn record(
+    repository: &Repository,
+    previous: Option<Oid>,
+    current: Oid,
+    action: &str,
+) -> Result<(), GitSyncError> {
+    let is_recorded = repository
+        .try_find_reference(HISTORY_REFERENCE)
+        .map_err(GitSyncError::from_gix)?
+        .is_some();
+
+    if let Some(previous) = previous.filter(|_| !is_recorded) {
+        update(repository, previous, &format!("start: at {previous}"))?;
+    }
+
+    update(
+        repository,
+        current,
+        &format!("{action}: moving to {current}"),
+    )
+}
+
+/// Roll back to the commit synced `steps` syncs before the current one.
+pub(crate) fn rollback_target(repository: &Repository, steps: usize) -> Result<Oid, GitSyncError> {
+    let synced = synced_commits(&read(repository)?);
+
+    synced
+        .iter()
+        .rev()
+        .nth(steps)
+        .copied()
+        .ok_or(GitSyncError::RollbackNotPossible {
+            steps,
+            recorded: synced.len().saturating_sub(1),
+        })
+}
+
+/// The synced commits that lead up to the current one, oldest first.
+///
+/// Rollbacks aren't syncs themselves: they undo the syncs after the commit
+/// they moved to, so that rolling back again goes further back rather than
+/// returning to the commit that was just backed out.
+fn synced_commits(history: &[SyncRecord]) -> Vec<Oid> {
+    let mut synced: Vec<Oid> = Vec::new();
+
+    for record in history.iter().rev() {
+        if !record.rollback {
+            synced.push(record.commit);
+            continue;
+        }
+
+        let current = synced.len().saturating_sub(1);
+        if let Some(position) = synced[..current]
+            .iter()
+            .rposition(|commit| *commit == record.commit)
+        {
+            synced.truncate(position + 1);
+        }
+    }
+
+    synced
+}
+
+/// Hold syncs back from `backed_out`, unless an earlier rollback already holds
+/// them back from a later commit.
+pub(crate) fn hold(repository: &Repository, backed_out: Oid) -> Result<(), GitSyncError> {
+    if held_back_from(repository)?.is_some() {
+        return Ok(());
+    }
+
+    repository
+        .reference(
+            ROLLBACK_REFERENCE,
+            backed_out,
+            PreviousValue::MustNotExist,
+            format!("rollback: holding back from {backed_out}"),
+        )
+        .map_err(GitSyncError::from_gix)?;
+
+    Ok(())
+}
+
+/// The commit a rollback holds syncs back from, if any.
+pub(crate) fn held_back_from(repository: &Repository) -> Result<Option<Oid>, GitSyncError> {
+    repository
+        .try_find_reference(ROLLBACK_REFERENCE)
+        .map_err(GitSyncError::from_gix)?
+        .map(|mut reference| reference.peel_to_id().map(gix::Id::detach))
+        .transpose()
+        .map_err(GitSyncError::from_gix)
+}
+
+/// Let syncs move forward again.
+pub(crate) fn release(repository: &Repository) -> Result<(), GitSyncError> {
+    if let Some(reference) = repository
+        .try_find_reference(ROLLBACK_REFERENCE)
+        .map_err(GitSyncError::from_gix)?
+    {
+        reference.delete().map_err(GitSyncError::from_gix)?;
+    }
+
+    Ok(())
+}
+
+/// The commits checked out so far, most recent (i.e. the current one) first.
+pub(crate) fn read(repository: &Repository) -> Result<Vec<SyncRecord>, GitSyncError> {
+    let reference = match repository
+        .try_find_reference(HISTORY_REFERENCE)
+        .map_err(GitSyncError::from_gix)?
+    {
+        Some(reference) => reference,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut log = reference.log_iter();
+    let lines = match log
+        .rev()
+        .map_err(|error| GitSyncError::GenericError { error })?
+    {
+        Some(lines) => lines,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut records = Vec::new();
+    for line in lines {
+        let line = line.map_err(GitSyncError::from_gix)?;
+        let seconds = line.signature.time.seconds;
+        let time = match u64::try_from(seconds) {
+            Ok(seconds) => UNIX_EPOCH + Duration::from_secs(seconds),
+            Err(_) => UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs()),
+        };
+
+        records.push(SyncRecord {
+            commit: line.new_oid,
+            previous: Some(line.previous_oid).filter(|id| !id.is_null()),
+            time,
+            rollback: line.message.starts_with_str("rollback:"),
+        });
+    }
+
+    Ok(records)
+}
+
+fn update(repository: &Repository, id: Oid, message: &str) -> Result<(), GitSyncError> {
+    repository
+        .edit_reference(RefEdit {
+            change: Change::Update {
+                log: LogChange {
+                    mode: RefLog::AndReference,
+                    force_create_reflog: true,
+                    message: message.into(),
+                },
+                expected: PreviousValue::Any,
+                new: gix::refs::Target::Object(id),
+            },
+            name: HISTORY_REFERENCE
+                .try_into()
+                .expect("the history reference name is valid"),
+            deref: false,
+        })
+        .map_err(GitSyncError::from_gix)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(commit: Oid, rollback: bool) -> SyncRecord {
+        SyncRecord {
+            commit,
+            previous: None,
+            time: UNIX_EPOCH,
+            rollback,
+        }
+    }
+
+    fn commit(byte: u8) -> Oid {
+        Oid::Sha1([byte; 20])
+    }
+
+    #[test]
+    fn rollbacks_undo_the_syncs_they_back_out() {
+        let (a, b, c) = (commit(1), commit(2), commit(3));
+
+        // Synced A, B and C, then rolled back to B and then to A
+        let history = [
+            record(a, true),
+            record(b, true),
+            record(c, false),
+            record(b, false),
+            record(a, false),
+        ];
+
+        assert_eq!(vec![a], synced_commits(&history));
+        assert_eq!(vec![a, b], synced_commits(&history[1..]));
+    }
+
+    #[test]
+    fn syncs_after_a_rollback_replace_the_backed_out_ones() {
+        let (a, b, c) = (commit(1), commit(2), commit(3));
+
+        // Synced A and B, rolled back to A, then synced C
+        let history = [
+            record(c, false),
+            record(a, true),
+            record(b, false),
+            record(a, false),
+        ];
+
+        assert_eq!(vec![a, c], synced_commits(&history));
+    }
+}
@@ -0,0 +1,576 @@
+use crate::credentials::Helpers;
+use crate::dirty::{DirtyKind, DirtyPaths};
+use crate::errors::GitSyncError;
+use crate::secret::redact_credentials;
+use crate::GitSync;
+use gix::bstr::ByteSlice;
+use gix::index::entry::{Flags, Mode, Stat};
+use gix::Repository;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(not(test))]
+use log::info;
+
+#[cfg(test)]
+use std::println as info;
+
+/// The first line of every LFS pointer file.
+const POINTER_VERSION: &str = "version https://git-lfs.github.com/spec/v1";
+
+/// Pointer files are never larger than this.
+const MAX_POINTER_SIZE: u64 = 1024;
+
+const MEDIA_TYPE: &str = "application/vnd.git-lfs+json";
+
+/// An LFS object, as described by the pointer file committed in its place.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Pointer {
+    oid: String,
+    size: u64,
+}
+
+/// Replace the LFS pointer files in `repository`'s worktree with the objects
+/// they point to, downloading those that aren't stored locally yet.
+pub(crate) fn checkout(gitsync: &GitSync, repository: &Repository) -> Result<(), GitSyncError> {
+    let workdir = repository.workdir().expect("syncs always have a worktree");
+    let mut index = repository.open_index().map_err(GitSyncError::from_gix)?;
+    let pointers = pointer_files(repository, &index, workdir)?;
+    if pointers.is_empty() {
+        return Ok(());
+    }
+
+    download(
+        gitsync,
+        repository,
+        pointers.values().map(|(_, pointer)| pointer),
+    )?;
+
+    let entries = index.entries_mut();
+    for (path, (position, pointer)) in pointers {
+        let entry = &mut entries[position];
+        let file = workdir.join(&path);
+        replace_with_object(repository, &pointer, &file, entry.mode)?;
+
+        // Keeps the replaced file from looking modified
+        entry.stat = gix::index::fs::Metadata::from_path_no_follow(&file)
+            .map_err(|error| GitSyncError::GenericError { error })
+            .and_then(|metadata| Stat::from_fs(&metadata).map_err(GitSyncError::from_gix))?;
+    }
+
+    index
+        .write(Default::default())
+        .map_err(GitSyncError::from_gix)?;
+
+    Ok(())
+}
+
+/// Replace the pointer files `index` checked out into `dir` with the objects
+/// they point to, for those that are stored locally.
+pub(crate) fn smudge(
+    repository: &Repository,
+    index: &gix::index::File,
+    dir: &Path,
+) -> Result<(), GitSyncError> {
+    for (path, (position, pointer)) in pointer_files(repository, index, dir)? {
+        if object_path(repository.git_dir(), &pointer).is_file() {
+            replace_with_object(
+                repository,
+                &pointer,
+                &dir.join(path),
+                index.entries()[position].mode,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Leave out the modified `paths` that are only different because the
+/// object their pointer file points to is checked out.
+pub(crate) fn retain_changed(
+    repository: &Repository,
+    paths: &mut DirtyPaths,
+) -> Result<(), GitSyncError> {
+    let workdir = repository.workdir().expect("syncs always have a worktree");
+    let index = repository
+        .index_or_empty()
+        .map_err(GitSyncError::from_gix)?;
+    let mut checked_out = Vec::new();
+
+    for (path, kind) in paths.iter() {
+        if *kind != DirtyKind::Modified {
+            continue;
+        }
+
+        let pointer = match index.entry_by_path(path.as_bstr()) {
+            Some(entry) => read_pointer(repository, entry.id)?,
+            None => None,
+        };
+        if let Some(pointer) = pointer {
+            let file = workdir.join(gix::path::from_bstr(path.as_bstr()));
+            if hash_file(&file)? == Some(pointer) {
+                checked_out.push(path.clone());
+            }
+        }
+    }
+
+    for path in checked_out {
+        paths.remove(&path);
+    }
+
+    Ok(())
+}
+
+/// The files in `index` that are committed as pointers and are still pointer
+/// files in `dir`, along with their positions in the index.
+fn pointer_files(
+    repository: &Repository,
+    index: &gix::index::File,
+    dir: &Path,
+) -> Result<BTreeMap<PathBuf, (usize, Pointer)>, GitSyncError> {
+    let mut pointers = BTreeMap::new();
+
+    for (position, entry) in index.entries().iter().enumerate() {
+        if entry.flags.contains(Flags::SKIP_WORKTREE)
+            || !matches!(entry.mode, Mode::FILE | Mode::FILE_EXECUTABLE)
+        {
+            continue;
+        }
+
+        let pointer = match read_pointer(repository, entry.id)? {
+            Some(pointer) => pointer,
+            None => continue,
+        };
+
+        let path = gix::path::from_bstr(entry.path(index)).into_owned();
+        let file = dir.join(&path);
+        let is_pointer_file = match std::fs::symlink_metadata(&file) {
+            Ok(metadata) if metadata.is_file() && metadata.len() <= MAX_POINTER_SIZE => {
+                std::fs::read(&file)
+                    .map_err(|error| GitSyncError::GenericError { error })
+                    .map(|contents| parse_pointer(&contents).is_some())?
+            }
+            _ => false,
+        };
+
+        if is_pointer_file {
+            pointers.insert(path, (position, pointer));
+        }
+    }
+
+    Ok(pointers)
+}
+
+fn read_pointer(
+    repository: &Repository,
+    id: gix::ObjectId,
+) -> Result<Option<Pointer>, GitSyncError> {
+    let header = repository.find_header(id).map_err(GitSyncError::from_gix)?;
+    if header.size() > MAX_POINTER_SIZE {
+        return Ok(None);
+    }
+
+    let blob = repository.find_blob(id).map_err(GitSyncError::from_gix)?;
+    Ok(parse_pointer(&blob.data))
+}
+
+fn parse_pointer(contents: &[u8]) -> Option<Pointer> {
+    let contents = contents.to_str().ok()?;
+    let mut lines = contents.lines();
+    if lines.next()? != POINTER_VERSION {
+        return None;
+    }
+
+    let mut oid = None;
+    let mut size = None;
+    for line in lines {
+        if let Some(value) = line.strip_prefix("oid sha256:") {
+            oid = Some(value)
+                .filter(|value| value.len() == 64)
+                .filter(|value| value.bytes().all(|byte| byte.is_ascii_hexdigit()))
+                .map(str::to_ascii_lowercase);
+        } else if let Some(value) = line.strip_prefix("size ") {
+            size = value.parse().ok();
+        }
+    }
+
+    Some(Pointer {
+        oid: oid?,
+        size: size?,
+    })
+}
+
+/// Download the objects among `pointers` that aren't stored locally, with
+/// the batch API of the remote's LFS server.
+fn download<'a>(
+    gitsync: &GitSync,
+    repository: &Repository,
+    pointers: impl Iterator<Item = &'a Pointer>,
+) -> Result<(), GitSyncError> {
+    let mut missing: Vec<&Pointer> = pointers
+        .filter(|pointer| !object_path(repository.git_dir(), pointer).is_file())
+        .collect();
+    missing.sort_by(|a, b| a.oid.cmp(&b.oid));
+    missing.dedup();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let endpoint = endpoint(gitsync, repository)?;
+    info!(
+        "Downloading {} LFS object(s) from {}",
+        missing.len(),
+        redact_credentials(&endpoint)
+    );
+
+    let helpers = match &gitsync.credential_helpers {
+        Some(helpers) => {
+            let url = gix::url::parse(endpoint.as_str().into()).map_err(GitSyncError::from_gix)?;
+            Some(helpers.for_url(repository, url)?)
+        }
+        None => None,
+    };
+
+    // reqwest's blocking client can't be used from within an async runtime,
+    // which whatever calls `sync` may well be in
+    let git_dir = repository.git_dir();
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| fetch(gitsync, helpers, git_dir, &endpoint, &missing))
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+/// Request the `missing` objects from the LFS server at `endpoint`, then
+/// store them in `git_dir`. The credentials come from `helpers` if there are
+/// any, which are told whether the server took them.
+fn fetch(
+    gitsync: &GitSync,
+    mut helpers: Option<Helpers>,
+    git_dir: &Path,
+    endpoint: &str,
+    missing: &[&Pointer],
+) -> Result<(), GitSyncError> {
+    let (credentials, next) = match helpers.as_mut() {
+        Some(helpers) => {
+            let (account, next) = helpers.get(endpoint)?;
+            (Some((account.username, account.password)), Some(next))
+        }
+        None => match gitsync.credential_provider() {
+            Some(provider) => {
+                let credentials = provider.credentials(endpoint).map_err(|error| {
+                    lfs_error(format!("the credentials couldn't be provided: {error}"))
+                })?;
+                let credentials = credentials.map(|credentials| {
+                    let username = credentials
+                        .username
+                        .or_else(|| gitsync.username.clone())
+                        .unwrap_or_else(|| "git".to_owned());
+                    (username, credentials.password.expose().to_owned())
+                });
+                (credentials, None)
+            }
+            None => (None, None),
+        },
+    };
+
+    let client = reqwest::blocking::Client::new();
+    let request = serde_json::json!({
+        "operation": "download",
+        "transfers": ["basic"],
+        "objects": missing
+            .iter()
+            .map(|pointer| serde_json::json!({ "oid": pointer.oid, "size": pointer.size }))
+            .collect::<Vec<_>>(),
+    });
+    let response = with_credentials(
+        &credentials,
+        client.post(format!("{endpoint}/objects/batch")),
+    )
+    .header("Accept", MEDIA_TYPE)
+    .header("Content-Type", MEDIA_TYPE)
+    .body(request.to_string())
+    .send()
+    .map_err(|error| lfs_error(format!("the batch request failed: {error}")))?;
+    if let (Some(helpers), Some(next)) = (helpers.as_mut(), next) {
+        let status = response.status();
+        if status.is_success() {
+            helpers.report(next, true)?;
+        } else if status == reqwest::StatusCode::UNAUTHORIZED {
+            helpers.report(next, false)?;
+        }
+    }
+    let response = response
+        .error_for_status()
+        .map_err(|error| lfs_error(format!("the batch request failed: {error}")))?;
+    let batch: serde_json::Value = response
+        .text()
+        .map_err(|error| lfs_error(format!("the batch response couldn't be read: {error}")))
+        .and_then(|text| {
+            serde_json::from_str(&text)
+                .map_err(|error| lfs_error(format!("the batch response isn't valid: {error}")))
+        })?;
+
+    for pointer in missing {
+        gitsync.ensure_not_cancelled()?;
+
+        let object = batch["objects"]
+            .as_array()
+            .and_then(|objects| {
+                objects
+                    .iter()
+                    .find(|object| object["oid"].as_str() == Some(pointer.oid.as_str()))
+            })
+            .ok_or_else(|| lfs_error(format!("the server didn't return object {}", pointer.oid)))?;
+        if let Some(message) = object["error"]["message"].as_str() {
+            return Err(lfs_error(format!("object {}: {message}", pointer.oid)));
+        }
+
+        let action = &object["actions"]["download"];
+        let href = action["href"]
+            .as_str()
+            .ok_or_else(|| lfs_error(format!("object {} has no download link", pointer.oid)))?;
+        let mut request = client.get(href);
+        match action["header"].as_object() {
+            Some(headers) => {
+                for (name, value) in headers {
+                    request = request.header(name.as_str(), value.as_str().unwrap_or_default());
+                }
+            }
+            // Don't hand the credentials to anywhere but the LFS server
+            None if is_same_origin(href, endpoint) => {
+                request = with_credentials(&credentials, request);
+            }
+            None => {}
+        }
+
+        let response = request
+            .send()
+            .and_then(reqwest::blocking::Response::error_for_status)
+            .map_err(|error| {
+                lfs_error(format!(
+                    "object {} couldn't be downloaded: {error}",
+                    pointer.oid
+                ))
+            })?;
+        store(git_dir, pointer, response)?;
+    }
+
+    Ok(())
+}
+
+/// The URL of the LFS server, from `lfs.url` in the repository's
+/// configuration or `.lfsconfig`, or else derived from the remote's URL as
+/// `git lfs` would.
+fn endpoint(gitsync: &GitSync, repository: &Repository) -> Result<String, GitSyncError> {
+    if let Some(url) = repository.config_snapshot().string("lfs.url") {
+        return Ok(url.to_str_lossy().trim_end_matches('/').to_owned());
+    }
+
+    let workdir = repository.workdir().expect("syncs always have a worktree");
+    let lfsconfig = workdir.join(".lfsconfig");
+    if lfsconfig.is_file() {
+        let config =
+            gix::config::File::from_path_no_includes(lfsconfig, gix::config::Source::Local)
+                .map_err(GitSyncError::from_gix)?;
+        if let Some(url) = config.string("lfs.url") {
+            return Ok(url.to_str_lossy().trim_end_matches('/').to_owned());
+        }
+    }
+
+    let url = gix::url::parse(gitsync.repo.as_str().into()).map_err(GitSyncError::from_gix)?;
+    let base = match (&url.scheme, url.host()) {
+        (gix::url::Scheme::Http | gix::url::Scheme::Https, _) => url
+            .to_bstring()
+            .to_str_lossy()
+            .trim_end_matches('/')
+            .to_owned(),
+        // SSH remotes are served over HTTPS by the same host
+        (gix::url::Scheme::Ssh, Some(host)) => format!(
+            "https://{host}/{}",
+            url.path.to_str_lossy().trim_matches('/')
+        ),
+        _ => {
+            return Err(lfs_error(format!(
+                "no LFS server is known for {}, so set lfs.url",
+                gitsync.repo
+            )))
+        }
+    };
+
+    if base.ends_with(".git") {
+        Ok(format!("{base}/info/lfs"))
+    } else {
+        Ok(format!("{base}.git/info/lfs"))
+    }
+}
+
+fn with_credentials(
+    credentials: &Option<(String, String)>,
+    request: reqwest::blocking::RequestBuilder,
+) -> reqwest::blocking::RequestBuilder {
+    match credentials {
+        Some((username, password)) => request.basic_auth(username, Some(password)),
+        None => request,
+    }
+}
+
+fn is_same_origin(href: &str, endpoint: &str) -> bool {
+    match (reqwest::Url::parse(href), reqwest::Url::parse(endpoint)) {
+        (Ok(href), Ok(endpoint)) => href.origin() == endpoint.origin(),
+        _ => false,
+    }
+}
+
+/// Where git-lfs keeps objects, so that the ones it already downloaded are
+/// reused.
+fn object_path(git_dir: &Path, pointer: &Pointer) -> PathBuf {
+    git_dir
+        .join("lfs")
+        .join("objects")
+        .join(&pointer.oid[0..2])
+        .join(&pointer.oid[2..4])
+        .join(&pointer.oid)
+}
+
+/// Write the object downloaded in `body` to the local store, once it is known
+/// to match `pointer`.
+fn store(git_dir: &Path, pointer: &Pointer, mut body: impl Read) -> Result<(), GitSyncError> {
+    let path = object_path(git_dir, pointer);
+    let dir = path.parent().expect("objects are stored in a directory");
+    std::fs::create_dir_all(dir).map_err(|error| GitSyncError::GenericError { error })?;
+
+    let mut file = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|error| GitSyncError::GenericError { error })?;
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = body.read(&mut buffer).map_err(|error| {
+            lfs_error(format!(
+                "object {} couldn't be downloaded: {error}",
+                pointer.oid
+            ))
+        })?;
+        if read == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..read]);
+        file.write_all(&buffer[..read])
+            .map_err(|error| GitSyncError::GenericError { error })?;
+        size += read as u64;
+    }
+
+    let downloaded = Pointer {
+        oid: format!("{:x}", hasher.finalize()),
+        size,
+    };
+    if downloaded != *pointer {
+        return Err(lfs_error(format!(
+            "the download of object {} doesn't match it",
+            pointer.oid
+        )));
+    }
+
+    file.persist(&path)
+        .map_err(|error| GitSyncError::GenericError { error: error.error })?;
+
+    Ok(())
+}
+
+/// Atomically replace `file` with a copy of the stored object.
+fn replace_with_object(
+    repository: &Repository,
+    pointer: &Pointer,
+    file: &Path,
+    mode: Mode,
+) -> Result<(), GitSyncError> {
+    let dir = file.parent().expect("worktree files are in a directory");
+    let copy = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|error| GitSyncError::GenericError { error })?;
+    std::fs::copy(object_path(repository.git_dir(), pointer), copy.path())
+        .map_err(|error| GitSyncError::GenericError { error })?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let permissions = match mode {
+            Mode::FILE_EXECUTABLE => 0o755,
+            _ => 0o644,
+        };
+        std::fs::set_permissions(copy.path(), std::fs::Permissions::from_mode(permissions))
+            .map_err(|error| GitSyncError::GenericError { error })?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+
+    copy.persist(file)
+        .map_err(|error| GitSyncError::GenericError { error: error.error })?;
+
+    Ok(())
+}
+
+/// The pointer `file` would have, if it's small enough to be an LFS object
+/// rather than a pointer file.
+fn hash_file(file: &Path) -> Result<Option<Pointer>, GitSyncError> {
+    let mut contents = match std::fs::File::open(file) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(GitSyncError::GenericError { error }),
+    };
+
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut contents, &mut hasher)
+        .map_err(|error| GitSyncError::GenericError { error })?;
+
+    Ok(Some(Pointer {
+        oid: format!("{:x}", hasher.finalize()),
+        size,
+    }))
+}
+
+fn lfs_error(reason: String) -> GitSyncError {
+    // reqwest's errors include the URL, which `lfs.url` may have put
+    // credentials in
+    GitSyncError::LfsDownloadFailed {
+        reason: redact_credentials(&reason).into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OID: &str = "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+
+    #[test]
+    fn pointers_are_parsed() {
+        let contents = format!("{POINTER_VERSION}\noid sha256:{OID}\nsize 12345\n");
+
+        assert_eq!(
+            Some(Pointer {
+                oid: OID.to_owned(),
+                size: 12345
+            }),
+            parse_pointer(contents.as_bytes())
+        );
+    }
+
+    #[test]
+    fn other_files_are_not_pointers() {
+        assert_eq!(None, parse_pointer(b"oid sha256:abc\nsize 1\n"));
+        assert_eq!(
+            None,
+            parse_pointer(format!("{POINTER_VERSION}\noid sha256:abc\nsize 1\n").as_bytes())
+        );
+        assert_eq!(
+            None,
+            parse_pointer(format!("{POINTER_VERSION}\noid sha256:{OID}\n").as_bytes())
+        );
+    }
+}
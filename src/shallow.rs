@@ -0,0 +1,58 @@
+use std::num::NonZeroU32;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How much history `bootstrap` clones, instead of all of it.
+///
+/// Syncing a shallow clone only fetches the commits that are new since the
+/// last sync, so the repository stays shallow without losing the history that
+/// fast-forwarding needs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Shallow {
+    /// Clone only the last `n` commits, with a depth of `1` fetching nothing
+    /// but the commit the branch points to.
+    Depth(NonZeroU32),
+    /// Clone only the commits made since the given time, which is
+    /// deserialised from seconds since the Unix epoch.
+    #[cfg_attr(
+        feature = "serde",
+        serde(deserialize_with = "crate::config::deserialize_since")
+    )]
+    Since(SystemTime),
+}
+
+impl From<Shallow> for gix::remote::fetch::Shallow {
+    fn from(shallow: Shallow) -> Self {
+        match shallow {
+            Shallow::Depth(depth) => gix::remote::fetch::Shallow::DepthAtRemote(depth),
+            Shallow::Since(since) => {
+                let seconds = match since.duration_since(UNIX_EPOCH) {
+                    Ok(after) => after.as_secs() as i64,
+                    Err(before) => -(before.duration().as_secs() as i64),
+                };
+                gix::remote::fetch::Shallow::Since {
+                    cutoff: gix::date::Time::new(seconds, 0),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn since_is_converted_to_a_utc_cutoff() {
+        let since = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        assert_eq!(
+            gix::remote::fetch::Shallow::from(Shallow::Since(since)),
+            gix::remote::fetch::Shallow::Since {
+                cutoff: gix::date::Time::new(1_700_000_000, 0)
+            }
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use crate::errors::GitSyncError;
+use crate::Oid;
+use gix::bstr::ByteSlice;
+use gix::Repository;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Which keys the commits a sync moves to must be signed with.
+///
+/// SSH signatures are checked with `ssh-keygen -Y verify` and GPG signatures
+/// with `gpgv`, so those programs need to be available when the matching
+/// trust store is configured.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct SignaturePolicy {
+    /// An `ssh-keygen` allowed signers file listing the trusted SSH keys.
+    pub allowed_signers: Option<PathBuf>,
+    /// An OpenPGP keyring (e.g. written by `gpg --export`) holding the trusted
+    /// GPG keys.
+    pub keyring: Option<PathBuf>,
+    /// Verify every commit between the previous and the new tip, rather than
+    /// only the new tip.
+    pub verify_all_commits: bool,
+}
+
+impl SignaturePolicy {
+    /// Verify the commits `repository` is about to move to from `previous`,
+    /// failing with `GitSyncError::UntrustedCommit` for the first one that
+    /// isn't signed by a trusted key.
+    pub(crate) fn verify(
+        &self,
+        repository: &Repository,
+        previous: Option<Oid>,
+        current: Oid,
+    ) -> Result<(), GitSyncError> {
+        let commits = match previous {
+            Some(previous) if self.verify_all_commits => repository
+                .rev_walk([current])
+                .with_hidden([previous])
+                .all()
+                .map_err(GitSyncError::from_gix)?
+                .map(|info| info.map(|info| info.id))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(GitSyncError::from_gix)?,
+            _ => vec![current],
+        };
+
+        for commit in commits {
+            self.verify_commit(repository, commit)?;
+        }
+
+        Ok(())
+    }
+
+    fn verify_commit(&self, repository: &Repository, id: Oid) -> Result<(), GitSyncError> {
+        let untrusted = |reason: &str| GitSyncError::UntrustedCommit {
+            commit: id,
+            reason: reason.to_owned(),
+        };
+
+        let commit = repository.find_commit(id).map_err(GitSyncError::from_gix)?;
+        let (signature, signed_data) = commit
+            .signature()
+            .map_err(GitSyncError::from_gix)?
+            .ok_or_else(|| untrusted("the commit isn't signed"))?;
+
+        let temp_dir = tempfile::Builder::new()
+            .prefix("gitsync-signature-")
+            .tempdir()
+            .map_err(|error| GitSyncError::GenericError { error })?;
+        let signature_file = temp_dir.path().join("signature");
+        std::fs::write(&signature_file, signature.as_bytes())
+            .map_err(|error| GitSyncError::GenericError { error })?;
+        let signed_data = signed_data.to_bstring();
+
+        let trusted = if signature.starts_with(b"-----BEGIN SSH SIGNATURE-----") {
+            let allowed_signers = self.allowed_signers.as_deref().ok_or_else(|| {
+                untrusted("the commit has an SSH signature, but no allowed signers are configured")
+            })?;
+            verify_ssh(allowed_signers, &signature_file, &signed_data)?
+        } else if signature.starts_with(b"-----BEGIN PGP SIGNATURE-----") {
+            let keyring = self.keyring.as_deref().ok_or_else(|| {
+                untrusted("the commit has a GPG signature, but no keyring is configured")
+            })?;
+            let data_file = temp_dir.path().join("data");
+            std::fs::write(&data_file, &signed_data)
+                .map_err(|error| GitSyncError::GenericError { error })?;
+            verify_gpg(keyring, temp_dir.path(), &signature_file, &data_file)?
+        } else {
+            return Err(untrusted(
+                "the commit's signature is of an unsupported format",
+            ));
+        };
+
+        if !trusted {
+            return Err(untrusted("the signature isn't from a trusted key"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Check an SSH signature against every principal of the allowed signers
+/// file that it could have been made by, as `git verify-commit` does.
+fn verify_ssh(
+    allowed_signers: &Path,
+    signature_file: &Path,
+    signed_data: &[u8],
+) -> Result<bool, GitSyncError> {
+    let output = Command::new("ssh-keygen")
+        .args(["-Y", "find-principals", "-f"])
+        .arg(allowed_signers)
+        .arg("-s")
+        .arg(signature_file)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|error| GitSyncError::GenericError { error })?;
+
+    if !output.status.success() {
+        return Ok(false);
+    }
+
+    for principal in output.stdout.lines().filter(|line| !line.is_empty()) {
+        let mut verify = Command::new("ssh-keygen")
+            .args(["-Y", "verify", "-n", "git", "-f"])
+            .arg(allowed_signers)
+            .arg("-I")
+            .arg(principal.to_os_str_lossy())
+            .arg("-s")
+            .arg(signature_file)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| GitSyncError::GenericError { error })?;
+
+        verify
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(signed_data)
+            .map_err(|error| GitSyncError::GenericError { error })?;
+
+        if verify
+            .wait()
+            .map_err(|error| GitSyncError::GenericError { error })?
+            .success()
+        {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn verify_gpg(
+    keyring: &Path,
+    home: &Path,
+    signature_file: &Path,
+    data_file: &Path,
+) -> Result<bool, GitSyncError> {
+    // gpgv reads relative keyring paths from its home directory
+    let keyring =
+        std::path::absolute(keyring).map_err(|error| GitSyncError::GenericError { error })?;
+
+    let status = Command::new("gpgv")
+        .arg("--homedir")
+        .arg(home)
+        .arg("--keyring")
+        .arg(keyring)
+        .arg(signature_file)
+        .arg(data_file)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|error| GitSyncError::GenericError { error })?;
+
+    Ok(status.success())
+}
@@ -0,0 +1,212 @@
+use crate::errors::GitSyncError;
+use gix::bstr::{BStr, ByteSlice};
+use gix::index::entry::Flags;
+use gix::Repository;
+use std::sync::atomic::AtomicBool;
+
+/// Check out `repository`'s HEAD into its (empty) worktree, leaving out every
+/// file outside of the cone-mode sparse checkout of `directories`, or nothing
+/// when there are none.
+///
+/// The sparse checkout is also written to the repository's configuration, so
+/// that later checkouts keep to it.
+pub(crate) fn checkout(
+    repository: &Repository,
+    directories: &[String],
+    interrupt: &AtomicBool,
+) -> Result<(), GitSyncError> {
+    let workdir = repository.workdir().expect("clones always have a worktree");
+    let tree_id = repository.head_tree_id().map_err(GitSyncError::from_gix)?;
+    let mut index = repository
+        .index_from_tree(&tree_id)
+        .map_err(GitSyncError::from_gix)?;
+
+    if !directories.is_empty() {
+        skip_outside_of_cone(&mut index, directories);
+    }
+
+    let mut options = repository
+        .checkout_options(gix::worktree::stack::state::attributes::Source::IdMapping)
+        .map_err(GitSyncError::from_gix)?;
+    options.destination_is_initially_empty = true;
+
+    gix::worktree::state::checkout(
+        &mut index,
+        workdir,
+        repository
+            .objects
+            .clone()
+            .into_arc()
+            .map_err(GitSyncError::from_gix)?,
+        &gix::progress::Discard,
+        &gix::progress::Discard,
+        interrupt,
+        options,
+    )
+    .map_err(GitSyncError::from_gix)?;
+
+    index
+        .write(Default::default())
+        .map_err(GitSyncError::from_gix)?;
+
+    if directories.is_empty() {
+        return Ok(());
+    }
+
+    write_patterns(repository, directories)
+}
+
+/// Mark the entries outside of the cone of `directories` so they aren't checked out.
+pub(crate) fn skip_outside_of_cone(index: &mut gix::index::File, directories: &[String]) {
+    for (entry, path) in index.entries_mut_with_paths() {
+        if !is_in_cone(path, directories) {
+            entry.flags.insert(Flags::EXTENDED | Flags::SKIP_WORKTREE);
+        }
+    }
+}
+
+/// Cone mode always includes the files at the root of the repository, all
+/// files directly inside the parents of `directories`, and everything below
+/// `directories` themselves.
+pub(crate) fn is_in_cone(path: &BStr, directories: &[String]) -> bool {
+    let parent = match path.rfind_byte(b'/') {
+        Some(index) => &path[..index],
+        None => return true,
+    };
+
+    directories.iter().any(|directory| {
+        let directory = directory.trim_matches('/').as_bytes();
+
+        path.starts_with(directory) && path.get(directory.len()) == Some(&b'/')
+            || directory.starts_with(parent) && directory.get(parent.len()) == Some(&b'/')
+    })
+}
+
+/// The directories of the cone-mode sparse checkout `repository` keeps to, or
+/// none when it checks out everything.
+pub(crate) fn directories(repository: &Repository) -> Result<Vec<String>, GitSyncError> {
+    let enabled = repository
+        .config_snapshot()
+        .boolean("core.sparseCheckout")
+        .unwrap_or(false);
+    if !enabled {
+        return Ok(Vec::new());
+    }
+
+    match std::fs::read_to_string(repository.git_dir().join("info").join("sparse-checkout")) {
+        Ok(patterns) => Ok(cone_directories(&patterns)),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(GitSyncError::GenericError { error }),
+    }
+}
+
+/// Read the directories back out of cone-mode `patterns`. Their parents are
+/// listed too, but are followed by a pattern excluding their subdirectories.
+fn cone_directories(patterns: &str) -> Vec<String> {
+    let patterns: Vec<&str> = patterns.lines().map(str::trim).collect();
+
+    patterns
+        .iter()
+        .filter(|pattern| pattern.len() > 1 && pattern.starts_with('/') && pattern.ends_with('/'))
+        .filter(|pattern| !patterns.contains(&format!("!{pattern}*/").as_str()))
+        .map(|pattern| pattern.trim_matches('/').to_owned())
+        .collect()
+}
+
+/// Enable cone-mode sparse checkout in the repository's configuration, as
+/// `git sparse-checkout set` would.
+fn write_patterns(repository: &Repository, directories: &[String]) -> Result<(), GitSyncError> {
+    let mut patterns = vec![String::from("/*"), String::from("!/*/")];
+    let mut parents = Vec::new();
+
+    for directory in directories {
+        let directory = directory.trim_matches('/');
+        let mut components: Vec<&str> = directory.split('/').collect();
+        components.pop();
+
+        let mut parent = String::new();
+        for component in components {
+            parent.push('/');
+            parent.push_str(component);
+            if !parents.contains(&parent) {
+                parents.push(parent.clone());
+            }
+        }
+    }
+
+    parents.sort();
+    for parent in parents {
+        patterns.push(format!("{parent}/"));
+        patterns.push(format!("!{parent}/*/"));
+    }
+    let mut directories: Vec<String> = directories
+        .iter()
+        .map(|directory| format!("/{}/", directory.trim_matches('/')))
+        .collect();
+    directories.sort();
+    patterns.extend(directories);
+
+    let info = repository.git_dir().join("info");
+    std::fs::create_dir_all(&info).map_err(|error| GitSyncError::GenericError { error })?;
+    std::fs::write(info.join("sparse-checkout"), patterns.join("\n") + "\n")
+        .map_err(|error| GitSyncError::GenericError { error })?;
+
+    crate::write_repository_config(
+        repository,
+        &[
+            ("core.sparseCheckout", "true"),
+            ("core.sparseCheckoutCone", "true"),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directories(directories: &[&str]) -> Vec<String> {
+        directories
+            .iter()
+            .map(|directory| directory.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn cone_includes_files_at_the_root() {
+        assert!(is_in_cone(
+            "README.md".into(),
+            &directories(&["deploy/prod"])
+        ));
+    }
+
+    #[test]
+    fn cone_includes_everything_below_its_directories() {
+        let directories = directories(&["deploy/prod"]);
+
+        assert!(is_in_cone("deploy/prod/app.yaml".into(), &directories));
+        assert!(is_in_cone(
+            "deploy/prod/db/values.yaml".into(),
+            &directories
+        ));
+        assert!(!is_in_cone(
+            "deploy/production/app.yaml".into(),
+            &directories
+        ));
+    }
+
+    #[test]
+    fn cone_includes_files_directly_inside_parent_directories() {
+        let directories = directories(&["deploy/prod"]);
+
+        assert!(is_in_cone("deploy/kustomization.yaml".into(), &directories));
+        assert!(!is_in_cone("deploy/staging/app.yaml".into(), &directories));
+        assert!(!is_in_cone("docs/index.md".into(), &directories));
+    }
+
+    #[test]
+    fn cone_directories_leave_out_their_parents() {
+        let patterns = "/*\n!/*/\n/deploy/\n!/deploy/*/\n/deploy/prod/\n/docs/\n";
+
+        assert_eq!(vec!["deploy/prod", "docs"], cone_directories(patterns));
+    }
+}
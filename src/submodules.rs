@@ -0,0 +1,236 @@
+use crate::errors::GitSyncError;
+use crate::ssh::SshOptions;
+use crate::{checkout, GitSync, Oid};
+use gix::bstr::ByteSlice;
+use gix::Repository;
+use std::path::{Path, PathBuf};
+
+#[cfg(not(test))]
+use log::info;
+
+#[cfg(test)]
+use std::println as info;
+
+/// A submodule that was cloned or moved to another commit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubmoduleUpdate {
+    /// Where the submodule is, relative to the top of the worktree.
+    pub path: PathBuf,
+    /// The commit it was at, unless it has just been cloned.
+    pub previous: Option<Oid>,
+    pub current: Oid,
+}
+
+/// Clone or update `repository`'s submodules, and theirs in turn, to the
+/// commits recorded in its index, as `git submodule update --init --recursive`
+/// would. `url` is the remote `repository` was cloned from.
+pub(crate) fn update(
+    gitsync: &GitSync,
+    repository: &Repository,
+    url: &str,
+    ssh: Option<&SshOptions>,
+) -> Result<Vec<SubmoduleUpdate>, GitSyncError> {
+    let mut updates = Vec::new();
+    update_recursively(gitsync, repository, url, Path::new(""), ssh, &mut updates)?;
+
+    Ok(updates)
+}
+
+fn update_recursively(
+    gitsync: &GitSync,
+    repository: &Repository,
+    url: &str,
+    prefix: &Path,
+    ssh: Option<&SshOptions>,
+    updates: &mut Vec<SubmoduleUpdate>,
+) -> Result<(), GitSyncError> {
+    let submodules = match repository.submodules().map_err(GitSyncError::from_gix)? {
+        Some(submodules) => submodules,
+        None => return Ok(()),
+    };
+
+    for submodule in submodules {
+        let commit = match submodule.index_id().map_err(GitSyncError::from_gix)? {
+            Some(commit) => commit,
+            None => continue,
+        };
+        let submodule_url = resolve_url(
+            url,
+            &submodule
+                .url()
+                .map_err(GitSyncError::from_gix)?
+                .to_bstring()
+                .to_str_lossy(),
+        );
+        let path = prefix.join(gix::path::from_bstr(
+            submodule.path().map_err(GitSyncError::from_gix)?.as_ref(),
+        ));
+        gitsync.ensure_not_cancelled()?;
+
+        let (submodule_repository, previous) =
+            match submodule.open().map_err(GitSyncError::from_gix)? {
+                Some(mut submodule_repository) => {
+                    let previous = submodule_repository
+                        .head()
+                        .map_err(GitSyncError::from_gix)?
+                        .try_peel_to_id()
+                        .map_err(GitSyncError::from_gix)?
+                        .map(gix::Id::detach);
+
+                    if previous != Some(commit) {
+                        check_out(gitsync, &mut submodule_repository, commit, ssh)?;
+                    }
+
+                    (submodule_repository, previous)
+                }
+                None => {
+                    let dir = submodule.work_dir().map_err(GitSyncError::from_gix)?;
+                    (clone(gitsync, &submodule_url, &dir, commit, ssh)?, None)
+                }
+            };
+
+        if previous != Some(commit) {
+            updates.push(SubmoduleUpdate {
+                path: path.clone(),
+                previous,
+                current: commit,
+            });
+        }
+
+        update_recursively(
+            gitsync,
+            &submodule_repository,
+            &submodule_url,
+            &path,
+            ssh,
+            updates,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Move an existing submodule to `commit`, fetching it first if need be.
+fn check_out(
+    gitsync: &GitSync,
+    repository: &mut Repository,
+    commit: Oid,
+    ssh: Option<&SshOptions>,
+) -> Result<(), GitSyncError> {
+    repository
+        .committer_or_set_generic_fallback()
+        .map_err(GitSyncError::from_gix)?;
+
+    if !repository.has_object(commit) {
+        gitsync.apply_ssh_overrides(repository, ssh)?;
+        gitsync.fetch(repository, ssh)?;
+        crate::ensure_revision_exists(repository, commit)?;
+    }
+
+    checkout::reset(repository, commit, &Default::default())?;
+    crate::detach_head(repository, commit, &format!("checkout: moving to {commit}"))
+}
+
+/// Clone the submodule at `url` into `dir`, checking out `commit`.
+fn clone(
+    gitsync: &GitSync,
+    url: &str,
+    dir: &Path,
+    commit: Oid,
+    ssh: Option<&SshOptions>,
+) -> Result<Repository, GitSyncError> {
+    info!(
+        "Attempting to clone submodule {} to {:?}",
+        crate::secret::redact_credentials(url),
+        dir
+    );
+    std::fs::create_dir_all(dir).map_err(|error| GitSyncError::GenericError { error })?;
+
+    let interrupt = gitsync.cancellation.as_interrupt();
+    let mut checkout = gitsync
+        .prepare_clone(url, dir, ssh)?
+        .fetch_then_checkout(gix::progress::Discard, interrupt)
+        .map_err(|error| gitsync.remote_error(ssh, error))?
+        .0;
+
+    crate::ensure_revision_exists(checkout.repo(), commit)?;
+    crate::detach_head(
+        checkout.repo(),
+        commit,
+        &format!("checkout: moving to {commit}"),
+    )?;
+
+    Ok(checkout
+        .main_worktree(gix::progress::Discard, interrupt)
+        .map_err(|error| gitsync.remote_error(None, error))?
+        .0)
+}
+
+/// Resolve a submodule's `url` against the `base` URL of the repository it
+/// belongs to, when it is relative (i.e. starts with `./` or `../`).
+fn resolve_url(base: &str, url: &str) -> String {
+    if !url.starts_with("./") && !url.starts_with("../") {
+        return url.to_owned();
+    }
+
+    let mut base = base.trim_end_matches('/').to_owned();
+    let mut url = url;
+    loop {
+        if let Some(rest) = url.strip_prefix("./") {
+            url = rest;
+        } else if let Some(rest) = url.strip_prefix("../") {
+            // `host:path` URLs keep their colon once the path is gone
+            match base.rfind(['/', ':']) {
+                Some(index) if base[index..].starts_with(':') => base.truncate(index + 1),
+                Some(index) => base.truncate(index),
+                None => base.clear(),
+            }
+            url = rest;
+        } else {
+            break;
+        }
+    }
+
+    if base.is_empty() || base.ends_with(':') {
+        format!("{base}{url}")
+    } else {
+        format!("{base}/{url}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn absolute_urls_are_kept() {
+        assert_eq!(
+            "https://example.com/lib.git",
+            resolve_url(
+                "https://example.com/org/app.git",
+                "https://example.com/lib.git"
+            )
+        );
+    }
+
+    #[test]
+    fn relative_urls_resolve_against_the_superproject() {
+        assert_eq!(
+            "https://example.com/org/lib.git",
+            resolve_url("https://example.com/org/app.git", "../lib.git")
+        );
+        assert_eq!(
+            "https://example.com/org/app.git/lib.git",
+            resolve_url("https://example.com/org/app.git/", "./lib.git")
+        );
+        assert_eq!(
+            "git@example.com:org/lib.git",
+            resolve_url("git@example.com:org/app.git", "../lib.git")
+        );
+        assert_eq!(
+            "git@example.com:lib.git",
+            resolve_url("git@example.com:app.git", "../lib.git")
+        );
+        assert_eq!("/srv/git/lib", resolve_url("/srv/git/app", "../lib"));
+    }
+}
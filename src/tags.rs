@@ -0,0 +1,123 @@
+use crate::errors::GitSyncError;
+use crate::Oid;
+use gix::bstr::ByteSlice;
+use gix::Repository;
+use std::collections::HashSet;
+use std::fmt;
+
+/// A tag for `GitSync` to follow instead of a branch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TagSelector {
+    /// The tag with exactly this name.
+    Exact(String),
+    /// The highest tag whose name, with any leading `v` removed, is a semantic
+    /// version matching the requirement (e.g. `>=1.2, <2`).
+    #[cfg_attr(feature = "serde", serde(rename = "semver"))]
+    SemVer(semver::VersionReq),
+}
+
+impl fmt::Display for TagSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TagSelector::Exact(name) => write!(f, "{name}"),
+            TagSelector::SemVer(requirement) => write!(f, "{requirement}"),
+        }
+    }
+}
+
+impl TagSelector {
+    /// Find the tag to check out among `repository`'s tags, returning its
+    /// name and the commit it points to.
+    pub(crate) fn select(&self, repository: &Repository) -> Result<(String, Oid), GitSyncError> {
+        let no_matching_tag = || GitSyncError::NoMatchingTag {
+            selector: self.to_string(),
+        };
+
+        let mut reference = match self {
+            TagSelector::Exact(name) => repository
+                .try_find_reference(format!("refs/tags/{name}").as_str())
+                .map_err(GitSyncError::from_gix)?
+                .ok_or_else(no_matching_tag)?,
+            TagSelector::SemVer(requirement) => {
+                let references = repository.references().map_err(GitSyncError::from_gix)?;
+                let mut highest = None;
+
+                for reference in references.tags().map_err(GitSyncError::from_gix)? {
+                    let reference = reference.map_err(|error| GitSyncError::GixError { error })?;
+                    let version = reference
+                        .name()
+                        .shorten()
+                        .to_str()
+                        .ok()
+                        .and_then(parse_version)
+                        .filter(|version| requirement.matches(version));
+
+                    if let Some(version) = version {
+                        if highest
+                            .as_ref()
+                            .is_none_or(|(highest, _)| version > *highest)
+                        {
+                            highest = Some((version, reference));
+                        }
+                    }
+                }
+
+                highest.ok_or_else(no_matching_tag)?.1
+            }
+        };
+
+        let name = reference.name().shorten().to_str_lossy().into_owned();
+        let id = reference
+            .peel_to_commit()
+            .map_err(GitSyncError::from_gix)?
+            .id;
+
+        Ok((name, id))
+    }
+}
+
+/// Delete the local tags that aren't among the `advertised` references, i.e.
+/// that the remote no longer has.
+pub(crate) fn prune(
+    repository: &Repository,
+    advertised: &[gix::protocol::handshake::Ref],
+) -> Result<(), GitSyncError> {
+    let advertised: HashSet<_> = advertised
+        .iter()
+        .map(|remote_ref| remote_ref.unpack().0)
+        .collect();
+
+    let references = repository.references().map_err(GitSyncError::from_gix)?;
+    for reference in references.tags().map_err(GitSyncError::from_gix)? {
+        let reference = reference.map_err(|error| GitSyncError::GixError { error })?;
+
+        if !advertised.contains(reference.name().as_bstr()) {
+            reference.delete().map_err(GitSyncError::from_gix)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_version(name: &str) -> Option<semver::Version> {
+    semver::Version::parse(name.strip_prefix('v').unwrap_or(name)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_may_have_a_leading_v() {
+        assert_eq!(Some(semver::Version::new(1, 2, 3)), parse_version("v1.2.3"));
+        assert_eq!(Some(semver::Version::new(1, 2, 3)), parse_version("1.2.3"));
+    }
+
+    #[test]
+    fn other_tag_names_are_not_versions() {
+        assert_eq!(None, parse_version("release"));
+        assert_eq!(None, parse_version("v1.2"));
+    }
+}
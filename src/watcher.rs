@@ -0,0 +1,136 @@
+use crate::errors::GitSyncError;
+use crate::secret::redact_credentials;
+use crate::{GitSync, SyncOutcome};
+use std::ops::ControlFlow;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+#[cfg(not(test))]
+use log::warn;
+
+#[cfg(test)]
+use std::println as warn;
+
+/// Keeps a clone up to date by bootstrapping it and then syncing it on an interval.
+///
+/// Failed attempts are retried with an exponential backoff, capped at
+/// `max_backoff`, and every attempt waits up to `jitter` longer than it has to
+/// so that a fleet of watchers doesn't hit the remote at the same time.
+#[derive(Clone, Debug)]
+pub struct Watcher {
+    gitsync: GitSync,
+    interval: Duration,
+    jitter: Duration,
+    max_backoff: Duration,
+}
+
+impl Watcher {
+    pub fn new(gitsync: GitSync, interval: Duration) -> Self {
+        Watcher {
+            gitsync,
+            interval,
+            jitter: Duration::ZERO,
+            max_backoff: Duration::from_secs(300).max(interval),
+        }
+    }
+
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Bootstrap and sync forever, passing the result of every attempt to
+    /// `on_sync` until it returns `ControlFlow::Break` or `GitSync::cancellation`
+    /// is cancelled.
+    pub fn run<F>(&self, mut on_sync: F)
+    where
+        F: FnMut(Result<SyncOutcome, GitSyncError>) -> ControlFlow<()>,
+    {
+        let mut failures = 0;
+
+        loop {
+            let result = self.gitsync.bootstrap().and_then(|_| self.gitsync.sync());
+
+            match &result {
+                Ok(_) => failures = 0,
+                Err(error) => {
+                    failures += 1;
+                    warn!(
+                        "Syncing {} failed ({failures} in a row): {error}",
+                        redact_credentials(&self.gitsync.repo)
+                    );
+                }
+            }
+
+            if on_sync(result).is_break() || self.gitsync.cancellation.is_cancelled() {
+                return;
+            }
+
+            let delay = self.delay(failures) + self.random_jitter();
+            if self.gitsync.cancellation.wait_timeout(delay) {
+                return;
+            }
+        }
+    }
+
+    /// Bootstrap and sync forever, sending the result of every attempt on
+    /// `sender` until its receiver is dropped or `GitSync::cancellation` is
+    /// cancelled.
+    pub fn run_with_channel(&self, sender: Sender<Result<SyncOutcome, GitSyncError>>) {
+        self.run(|result| match sender.send(result) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(()),
+        })
+    }
+
+    fn delay(&self, failures: u32) -> Duration {
+        if failures == 0 {
+            return self.interval;
+        }
+
+        self.interval
+            .checked_mul(2u32.saturating_pow(failures))
+            .unwrap_or(Duration::MAX)
+            .min(self.max_backoff)
+    }
+
+    fn random_jitter(&self) -> Duration {
+        if self.jitter.is_zero() {
+            return Duration::ZERO;
+        }
+
+        self.jitter.mul_f64(fastrand::f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_backs_off_exponentially_up_to_the_maximum() {
+        let watcher = Watcher::new(GitSync::default(), Duration::from_secs(10))
+            .with_max_backoff(Duration::from_secs(60));
+
+        assert_eq!(watcher.delay(0), Duration::from_secs(10));
+        assert_eq!(watcher.delay(1), Duration::from_secs(20));
+        assert_eq!(watcher.delay(2), Duration::from_secs(40));
+        assert_eq!(watcher.delay(3), Duration::from_secs(60));
+        assert_eq!(watcher.delay(64), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let watcher = Watcher::new(GitSync::default(), Duration::from_secs(10))
+            .with_jitter(Duration::from_secs(2));
+
+        for _ in 0..100 {
+            assert!(watcher.random_jitter() <= Duration::from_secs(2));
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use cucumber::World as _;
 use gitsync::errors;
 use std::path::PathBuf;
+use std::sync::Mutex;
 
 mod steps;
 
@@ -17,9 +18,67 @@ pub struct World {
     sync_error: Option<errors::GitSyncError>,
     sync_outcome: Option<gitsync::SyncOutcome>,
     created_files: Vec<String>,
+    private_key: Option<String>,
+    passphrase: Option<String>,
+    known_hosts: Vec<String>,
+    watch_results: Vec<Result<gitsync::SyncOutcome, String>>,
+    cancellation: gitsync::CancellationToken,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    sparse_checkout: Vec<String>,
+    signature_policy: Option<gitsync::SignaturePolicy>,
+    tag: Option<gitsync::TagSelector>,
+    revision: Option<gitsync::Oid>,
+    divergence: gitsync::DivergenceStrategy,
+    dirty_worktree: gitsync::DirtyWorktreePolicy,
+    deployment: Option<gitsync::Deployment>,
+    submodules: bool,
+    #[cfg(feature = "lfs")]
+    lfs: bool,
+    password: Option<String>,
+    credential_helpers: Option<gitsync::CredentialHelpers>,
+    credential_provider: Option<std::sync::Arc<dyn gitsync::CredentialProvider>>,
+}
+
+/// Everything gitsync logs, so that scenarios can check what it gives away.
+static LOGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct Logger;
+
+impl log::Log for Logger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        LOGS.lock().unwrap().push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+pub fn logs() -> Vec<String> {
+    LOGS.lock().unwrap().clone()
 }
 
 #[tokio::main]
 async fn main() {
-    World::run("./features").await;
+    // SSH remotes are served by a local stand-in rather than a real sshd
+    std::env::set_var(
+        "GIT_SSH_COMMAND",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/ssh-stand-in"),
+    );
+
+    log::set_logger(&Logger).expect("no other logger is set");
+    log::set_max_level(log::LevelFilter::Info);
+
+    World::cucumber()
+        .filter_run("./features", |feature, _, _| {
+            // Scenarios for optional features only run when they're enabled
+            let has_tag = |name: &str| feature.tags.iter().any(|tag| tag == name);
+
+            (cfg!(feature = "async") || !has_tag("async"))
+                && (cfg!(feature = "lfs") || !has_tag("lfs"))
+        })
+        .await;
 }
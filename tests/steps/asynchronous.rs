@@ -0,0 +1,51 @@
+use cucumber::{then, when};
+
+use crate::World;
+
+#[when("I bootstrap asynchronously")]
+async fn bootstrap_asynchronously(world: &mut World) {
+    world.sync_error = gitsync(world).bootstrap_async().await.err();
+}
+
+#[when("I sync asynchronously")]
+async fn sync_asynchronously(world: &mut World) {
+    match gitsync(world).sync_async().await {
+        Ok(outcome) => {
+            world.sync_outcome = Some(outcome);
+            world.sync_error = None;
+        }
+        Err(error) => {
+            world.sync_outcome = None;
+            world.sync_error = Some(error);
+        }
+    }
+}
+
+#[then("head_oid_async matches HEAD")]
+async fn head_oid_async_matches_head(world: &mut World) {
+    let head_oid = gitsync(world)
+        .head_oid_async()
+        .await
+        .expect("head oid can be read")
+        .expect("head exists");
+    let output = std::process::Command::new("git")
+        .current_dir(&world.clone_dir)
+        .arg("rev-parse")
+        .arg("HEAD")
+        .output()
+        .expect("Failed to get current commit hash");
+    assert!(output.status.success());
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        head_oid.to_string()
+    );
+}
+
+fn gitsync(world: &World) -> gitsync::GitSync {
+    gitsync::GitSync {
+        repo: world.repo_url.clone(),
+        dir: world.clone_dir.clone(),
+        ..Default::default()
+    }
+}
@@ -82,12 +82,29 @@ fn bootstrap_git_repository(world: &mut World) {
     let gitsync = gitsync::GitSync {
         repo: String::from(world.bare_dir.clone().to_str().unwrap()),
         dir: world.clone_dir.clone(),
+        cancellation: world.cancellation.clone(),
+        sparse_checkout: world.sparse_checkout.clone(),
+        signature_policy: world.signature_policy.clone(),
+        tag: world.tag.clone(),
+        revision: world.revision,
+        deployment: world.deployment.clone(),
+        submodules: world.submodules,
+        #[cfg(feature = "lfs")]
+        lfs: world.lfs,
+        password: world.password.clone().map(Into::into),
+        credential_helpers: world.credential_helpers.clone(),
+        credential_provider: world.credential_provider.clone(),
         ..Default::default()
     };
 
     world.sync_error = gitsync.bootstrap().err();
 }
 
+#[when("I bootstrap without git on the PATH")]
+fn bootstrap_without_git(world: &mut World) {
+    crate::steps::sync::without_git_on_path(|| bootstrap_git_repository(world));
+}
+
 #[when(regex = r#"I bootstrap branch "(\S+)""#)]
 fn bootstrap_git_repository_branch(world: &mut World, branch: String) {
     world.repo_url = String::from(world.bare_dir.to_str().unwrap());
@@ -97,6 +114,7 @@ fn bootstrap_git_repository_branch(world: &mut World, branch: String) {
         repo: String::from(world.bare_dir.clone().to_str().unwrap()),
         dir: world.clone_dir.clone(),
         branch: Some(branch),
+        cancellation: world.cancellation.clone(),
         ..Default::default()
     };
 
@@ -118,6 +136,11 @@ fn repository_is_cloned(world: &mut World) {
     assert_eq!(format!("{repo_url}\n").as_bytes(), output.stdout.as_slice());
 }
 
+#[then(regex = r#"there is no directory called "(\S+)"$"#)]
+fn there_is_no_directory(world: &mut World, directory: String) {
+    assert!(!world.test_dir.join(directory).exists());
+}
+
 #[then("the directory is left untouched")]
 fn directory_left_untouched(world: &mut World) {
     assert!(world.clone_dir.is_dir());
@@ -129,7 +152,7 @@ fn directory_left_untouched(world: &mut World) {
 
 #[then("the bootstrap completes")]
 fn bootstrap_is_ok(world: &mut World) {
-    assert!(world.sync_error.is_none());
+    assert!(world.sync_error.is_none(), "{:?}", world.sync_error);
 }
 
 #[then(regex = r#"the checked out branch is "(\S+)""#)]
@@ -159,12 +182,27 @@ fn bootstrap_errors_because(world: &mut World, error: String) {
         "local dir isn't git repository" => {
             assert!(matches!(w, errors::GitSyncError::GixError { .. }))
         }
+        "invalid private key" => {
+            assert!(matches!(w, errors::GitSyncError::InvalidPrivateKey { .. }))
+        }
+        "cancelled" => {
+            assert!(matches!(w, errors::GitSyncError::Cancelled))
+        }
+        "host key mismatch" => {
+            assert!(matches!(
+                w,
+                errors::GitSyncError::HostKeyVerificationFailed { .. }
+            ))
+        }
         "incorrect remote" => {
             assert!(matches!(
                 w,
                 errors::GitSyncError::IncorrectGitRemotes { .. }
             ))
         }
+        "untrusted commit" => {
+            assert!(matches!(w, errors::GitSyncError::UntrustedCommit { .. }))
+        }
         _ => panic!("Unknown error type"),
     };
 }
@@ -0,0 +1,112 @@
+use cucumber::gherkin::Step;
+use cucumber::{given, then};
+use gitsync::FileChange;
+use std::path::Path;
+
+use crate::World;
+
+#[given("the remote commits these changes:")]
+fn remote_commits_changes(world: &mut World, step: &Step) {
+    let table = step.table.as_ref().expect("a table of changes");
+
+    for row in table.rows.iter().skip(1) {
+        let (change, path) = (row[0].as_str(), row[1].as_str());
+        let file = world.source_dir.join(path);
+
+        match change {
+            "add" | "modify" => {
+                std::fs::create_dir_all(file.parent().unwrap())
+                    .expect("Failed to create directory");
+                let contents = match change {
+                    "add" => format!("contents of {path}\n"),
+                    _ => format!("modified contents of {path}\n"),
+                };
+                std::fs::write(&file, contents).expect("Failed to write file");
+                git(&world.source_dir, &["add", path]);
+            }
+            "delete" => git(&world.source_dir, &["rm", "-q", path]),
+            "rename" => {
+                let (from, to) = path.split_once(" -> ").expect("rename is `from -> to`");
+                std::fs::create_dir_all(world.source_dir.join(to).parent().unwrap())
+                    .expect("Failed to create directory");
+                git(&world.source_dir, &["mv", from, to]);
+            }
+            _ => panic!("Unknown change {}", change),
+        }
+    }
+
+    git(&world.source_dir, &["commit", "-q", "-m", "changes"]);
+    git(&world.source_dir, &["push", "-q", "origin", "HEAD"]);
+}
+
+#[given(regex = r#"I only watch "(\S+)"$"#)]
+fn i_only_watch(world: &mut World, pattern: String) {
+    world.include.push(pattern);
+}
+
+#[given(regex = r#"I don't watch "(\S+)"$"#)]
+fn i_do_not_watch(world: &mut World, pattern: String) {
+    world.exclude.push(pattern);
+}
+
+#[then("the sync reports these changed files:")]
+fn sync_reports_changed_files(world: &mut World, step: &Step) {
+    let table = step.table.as_ref().expect("a table of changed files");
+    let outcome = world.sync_outcome.as_ref().expect("sync outcome");
+
+    let mut expected: Vec<String> = table
+        .rows
+        .iter()
+        .skip(1)
+        .map(|row| format!("{} {}", row[0], row[1]))
+        .collect();
+    let mut actual: Vec<String> = outcome.changes.iter().map(describe).collect();
+
+    expected.sort();
+    actual.sort();
+    assert_eq!(expected, actual);
+}
+
+#[then("the sync reports no changed files")]
+fn sync_reports_no_changed_files(world: &mut World) {
+    let outcome = world.sync_outcome.as_ref().expect("sync outcome");
+    assert_eq!(Vec::<FileChange>::new(), outcome.changes);
+}
+
+fn describe(change: &FileChange) -> String {
+    match change {
+        FileChange::Added { path, .. } => format!("added {}", path.display()),
+        FileChange::Modified {
+            path,
+            previous_id,
+            id,
+        } => {
+            assert_ne!(previous_id, id);
+            format!("modified {}", path.display())
+        }
+        FileChange::Deleted { path, .. } => format!("deleted {}", path.display()),
+        FileChange::Renamed {
+            previous_path,
+            path,
+            previous_id,
+            id,
+        } => {
+            assert_eq!(previous_id, id);
+            format!("renamed {} -> {}", previous_path.display(), path.display())
+        }
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let output = std::process::Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("Failed to run git");
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {:?}",
+        args,
+        output
+    );
+}
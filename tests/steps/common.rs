@@ -146,3 +146,47 @@ fn i_have_a_git_repository(world: &mut World, directory: String) {
 
     world.clone_dir = clone_dir;
 }
+
+#[given("the operation has been cancelled")]
+fn operation_cancelled(world: &mut World) {
+    world.cancellation.cancel();
+}
+
+#[given("there are new commits on the remote")]
+fn new_commits_on_remote(world: &mut World) {
+    let output = std::process::Command::new("git")
+        .current_dir(&world.clone_dir)
+        .arg("rev-parse")
+        .arg("HEAD")
+        .output()
+        .expect("Failed to get current commit hash");
+    assert!(output.status.success());
+    world.current_commit_hash = output.stdout;
+
+    let file = world.source_dir.join("file");
+    let contents = std::fs::read_to_string(&file).expect("Failed to read file");
+    std::fs::write(&file, contents + "3").expect("Failed to write file");
+
+    let output = std::process::Command::new("git")
+        .current_dir(&world.source_dir)
+        .args(vec!["commit", "-am", "3"])
+        .output()
+        .expect("Failed to commit file");
+    assert!(output.status.success());
+
+    let output = std::process::Command::new("git")
+        .current_dir(&world.source_dir)
+        .args(vec!["push", "origin", "HEAD"])
+        .output()
+        .expect("Failed to push changes");
+    assert!(output.status.success());
+
+    let output = std::process::Command::new("git")
+        .current_dir(&world.source_dir)
+        .arg("rev-parse")
+        .arg("HEAD")
+        .output()
+        .expect("Failed to get latest commit hash");
+    assert!(output.status.success());
+    world.latest_commit_hash = output.stdout;
+}
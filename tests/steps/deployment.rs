@@ -0,0 +1,63 @@
+use cucumber::{given, then};
+use std::path::PathBuf;
+
+use crate::World;
+
+#[given(regex = r#"I deploy to "(\S+)" keeping (\d+) previous revisions?$"#)]
+fn i_deploy_to(world: &mut World, link: String, generations: usize) {
+    world.deployment = Some(gitsync::Deployment {
+        link: world.test_dir.join(link),
+        revisions: world.test_dir.join("revisions"),
+        generations,
+    });
+}
+
+#[then(regex = r#""(\S+)" points at the commit the clone is at$"#)]
+fn link_points_at_clone_commit(world: &mut World, link: String) {
+    let output = std::process::Command::new("git")
+        .current_dir(&world.clone_dir)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .expect("Failed to get current commit hash");
+    assert!(output.status.success());
+
+    let link = world.test_dir.join(link);
+    let target = std::fs::read_link(&link).expect("Failed to read symlink");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        target.file_name().unwrap().to_string_lossy()
+    );
+    assert_eq!(
+        std::fs::read_to_string(world.clone_dir.join("file")).expect("Failed to read file"),
+        std::fs::read_to_string(link.join("file")).expect("Failed to read deployed file")
+    );
+}
+
+#[then("the sync reports the deployment")]
+fn sync_reports_deployment(world: &mut World) {
+    let outcome = world.sync_outcome.as_ref().expect("sync outcome");
+    let link = &world.deployment.as_ref().expect("a deployment").link;
+
+    assert_eq!(
+        std::fs::read_link(link).ok(),
+        outcome.deployment.as_ref().map(PathBuf::from)
+    );
+}
+
+#[then(regex = r#"there (?:is|are) (\d+) deployed revisions?$"#)]
+fn deployed_revisions(world: &mut World, count: usize) {
+    let revisions = &world.deployment.as_ref().expect("a deployment").revisions;
+    let deployed = std::fs::read_dir(revisions)
+        .expect("Failed to list revisions")
+        .filter(|entry| {
+            !entry
+                .as_ref()
+                .expect("Failed to read revision")
+                .file_name()
+                .to_string_lossy()
+                .starts_with('.')
+        })
+        .count();
+
+    assert_eq!(count, deployed);
+}
@@ -0,0 +1,70 @@
+use cucumber::{given, then};
+
+use crate::World;
+
+#[given(regex = r#"there is an untracked file called "(\S+)"$"#)]
+fn untracked_file(world: &mut World, path: String) {
+    let file = world.clone_dir.join(&path);
+    std::fs::create_dir_all(file.parent().unwrap()).expect("Failed to create directory");
+    std::fs::write(file, "untracked").expect("Failed to write untracked file");
+}
+
+#[given("I discard local changes")]
+fn i_discard_local_changes(world: &mut World) {
+    world.dirty_worktree = gitsync::DirtyWorktreePolicy::Discard;
+}
+
+#[given("I stash local changes")]
+fn i_stash_local_changes(world: &mut World) {
+    world.dirty_worktree = gitsync::DirtyWorktreePolicy::Stash;
+}
+
+#[given(regex = r#"I back up local changes to "(\S+)"$"#)]
+fn i_back_up_local_changes(world: &mut World, directory: String) {
+    world.dirty_worktree = gitsync::DirtyWorktreePolicy::Backup(world.test_dir.join(directory));
+}
+
+#[then("the worktree is clean")]
+fn worktree_is_clean(world: &mut World) {
+    let output = std::process::Command::new("git")
+        .current_dir(&world.clone_dir)
+        .args(["status", "--porcelain", "--untracked-files=all"])
+        .output()
+        .expect("Failed to get the worktree status");
+    assert!(output.status.success());
+    assert_eq!("", String::from_utf8_lossy(&output.stdout));
+}
+
+#[then(regex = r#"the stash has "(\S+)" containing "(.*)"$"#)]
+fn stash_has_file(world: &mut World, path: String, contents: String) {
+    let output = std::process::Command::new("git")
+        .current_dir(&world.clone_dir)
+        .arg("show")
+        .arg(format!("{}:{path}", gitsync::STASH_REFERENCE))
+        .output()
+        .expect("Failed to read the stash");
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(contents, String::from_utf8_lossy(&output.stdout));
+}
+
+#[then(regex = r#"the backup in "(\S+)" has "(\S+)" containing "(.*)"$"#)]
+fn backup_has_file(world: &mut World, directory: String, path: String, contents: String) {
+    let backups: Vec<_> = std::fs::read_dir(world.test_dir.join(directory))
+        .expect("Failed to list backups")
+        .collect::<Result<_, _>>()
+        .expect("Failed to list backups");
+    assert_eq!(1, backups.len());
+
+    assert_eq!(
+        contents,
+        std::fs::read_to_string(backups[0].path().join(path)).expect("Failed to read backup")
+    );
+}
+
+#[then(regex = r#"the worktree has "(\S+)" containing "(.*)"$"#)]
+fn worktree_has_file(world: &mut World, path: String, contents: String) {
+    assert_eq!(
+        contents,
+        std::fs::read_to_string(world.clone_dir.join(path)).expect("Failed to read file")
+    );
+}
@@ -0,0 +1,125 @@
+use cucumber::{given, then};
+use std::path::Path;
+
+use crate::World;
+
+#[given(regex = r#"I follow force-pushes by "(hard-reset|rebase|merge)"$"#)]
+fn i_follow_force_pushes(world: &mut World, strategy: String) {
+    world.divergence = match strategy.as_str() {
+        "hard-reset" => gitsync::DivergenceStrategy::HardReset,
+        "rebase" => gitsync::DivergenceStrategy::Rebase,
+        _ => gitsync::DivergenceStrategy::Merge,
+    };
+}
+
+#[given("the remote is force-pushed")]
+fn remote_is_force_pushed(world: &mut World) {
+    world.current_commit_hash = rev_parse(&world.clone_dir, "HEAD");
+
+    std::fs::write(world.source_dir.join("file"), "12 amended").expect("Failed to write file");
+    git(
+        &world.source_dir,
+        &["commit", "-q", "--amend", "-am", "2 amended"],
+    );
+    git(
+        &world.source_dir,
+        &["push", "-q", "--force", "origin", "HEAD"],
+    );
+
+    world.latest_commit_hash = rev_parse(&world.source_dir, "HEAD");
+}
+
+#[given("there is a conflicting local commit")]
+fn conflicting_local_commit(world: &mut World) {
+    std::fs::write(world.clone_dir.join("file"), "conflict").expect("Failed to write file");
+    git(
+        &world.clone_dir,
+        &[
+            "-c",
+            "user.name=Example Author",
+            "-c",
+            "user.email=example@example.com",
+            "commit",
+            "-q",
+            "-am",
+            "conflict",
+        ],
+    );
+}
+
+#[then("the sync reports rewritten history")]
+fn sync_reports_rewritten_history(world: &mut World) {
+    let outcome = world.sync_outcome.as_ref().expect("sync outcome");
+    assert!(outcome.rewritten);
+}
+
+#[then("the sync reports history wasn't rewritten")]
+fn sync_reports_history_not_rewritten(world: &mut World) {
+    let outcome = world.sync_outcome.as_ref().expect("sync outcome");
+    assert!(!outcome.rewritten);
+}
+
+#[then("the local commits are rebased onto the remote")]
+fn local_commits_rebased(world: &mut World) {
+    assert_eq!(
+        rev_parse(&world.source_dir, "HEAD"),
+        rev_parse(&world.clone_dir, "HEAD^")
+    );
+    assert_eq!(b"local\n".to_vec(), subject(&world.clone_dir));
+    assert!(world.clone_dir.join("local-file").is_file());
+    assert_eq!(
+        "12 amended",
+        std::fs::read_to_string(world.clone_dir.join("file")).expect("Failed to read file")
+    );
+}
+
+#[then("the remote is merged into the local commits")]
+fn remote_merged(world: &mut World) {
+    assert_eq!(
+        rev_parse(&world.source_dir, "HEAD"),
+        rev_parse(&world.clone_dir, "HEAD^2")
+    );
+    assert_eq!(
+        world.current_commit_hash,
+        rev_parse(&world.clone_dir, "HEAD^1")
+    );
+    assert!(world.clone_dir.join("local-file").is_file());
+    assert_eq!(
+        "123",
+        std::fs::read_to_string(world.clone_dir.join("file")).expect("Failed to read file")
+    );
+}
+
+fn subject(dir: &Path) -> Vec<u8> {
+    let output = std::process::Command::new("git")
+        .current_dir(dir)
+        .args(["log", "-1", "--format=%s"])
+        .output()
+        .expect("Failed to read the commit subject");
+    assert!(output.status.success());
+    output.stdout
+}
+
+fn rev_parse(dir: &Path, revision: &str) -> Vec<u8> {
+    let output = std::process::Command::new("git")
+        .current_dir(dir)
+        .args(["rev-parse", revision])
+        .output()
+        .expect("Failed to parse revision");
+    assert!(output.status.success());
+    output.stdout
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let output = std::process::Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("Failed to run git");
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {:?}",
+        args,
+        output
+    );
+}
@@ -0,0 +1,225 @@
+use base64::Engine;
+use cucumber::{given, then};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+
+use crate::World;
+
+#[given("I download LFS objects")]
+fn i_download_lfs_objects(world: &mut World) {
+    world.lfs = true;
+}
+
+#[given(regex = r#"I use the password "(\S+)"$"#)]
+fn i_use_the_password(world: &mut World, password: String) {
+    world.password = Some(password);
+}
+
+#[given(regex = r#"I use a credential helper answering the password "(\S+)"$"#)]
+fn i_use_a_credential_helper(world: &mut World, password: String) {
+    // Log what the helper is asked to do, and answer `get` with the password
+    let helper = format!(
+        "!f() {{ echo $1 >> '{}'; test $1 != get || {{ echo username=git; echo password={password}; }}; }}; f",
+        credential_helper_log(world).display()
+    );
+    world.credential_helpers = Some(gitsync::CredentialHelpers {
+        configured: false,
+        programs: vec![helper],
+    });
+}
+
+#[given(regex = r#"the credentials file contains the password "(\S+)"$"#)]
+fn credentials_file_contains(world: &mut World, password: String) {
+    let password_file = world.test_dir.join("credentials");
+    std::fs::write(&password_file, format!("{password}\n")).unwrap();
+
+    world.credential_provider = Some(std::sync::Arc::new(gitsync::FileCredentials {
+        username: None,
+        password_file,
+    }));
+}
+
+#[then(regex = r#"the credential helper was asked to "(\S+)" the password$"#)]
+fn credential_helper_was_asked(world: &mut World, action: String) {
+    let log = std::fs::read_to_string(credential_helper_log(world)).unwrap_or_default();
+
+    assert!(
+        log.lines().any(|line| line == action),
+        "the credential helper was only asked to {:?}",
+        log.lines().collect::<Vec<_>>()
+    );
+}
+
+#[given("the remote has an LFS server")]
+fn remote_has_lfs_server(world: &mut World) {
+    start_lfs_server(world, None);
+}
+
+#[given(regex = r#"the remote has an LFS server requiring the password "(\S+)"$"#)]
+fn remote_has_lfs_server_with_password(world: &mut World, password: String) {
+    start_lfs_server(world, Some(password));
+}
+
+#[given(regex = r#"the remote has an LFS file "(\S+)" containing "(.*)"$"#)]
+fn remote_has_lfs_file(world: &mut World, path: String, contents: String) {
+    let oid = format!("{:x}", Sha256::digest(contents.as_bytes()));
+    std::fs::write(lfs_objects(world).join(&oid), &contents).expect("Failed to store object");
+
+    let file = world.source_dir.join(&path);
+    std::fs::create_dir_all(file.parent().unwrap()).expect("Failed to create directory");
+    std::fs::write(
+        &file,
+        format!(
+            "version https://git-lfs.github.com/spec/v1\noid sha256:{oid}\nsize {}\n",
+            contents.len()
+        ),
+    )
+    .expect("Failed to write pointer file");
+
+    commit_and_push(&world.source_dir, &path);
+}
+
+#[then(regex = r#"the deployment has "(\S+)" containing "(.*)"$"#)]
+fn deployment_has_file(world: &mut World, path: String, contents: String) {
+    let link = &world.deployment.as_ref().expect("deployment").link;
+
+    assert_eq!(
+        contents,
+        std::fs::read_to_string(link.join(path)).expect("Failed to read deployed file")
+    );
+}
+
+/// Serve the objects in the test directory's `lfs-objects` with the LFS
+/// batch API, then point the remote at it and have it track `*.bin` files.
+fn start_lfs_server(world: &mut World, password: Option<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to start LFS server");
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let objects = lfs_objects(world);
+    std::fs::create_dir_all(&objects).expect("Failed to create LFS objects directory");
+
+    let server_url = url.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            respond(stream, &server_url, &objects, password.as_deref());
+        }
+    });
+
+    std::fs::write(
+        world.source_dir.join(".gitattributes"),
+        "*.bin filter=lfs diff=lfs merge=lfs -text\n",
+    )
+    .expect("Failed to write .gitattributes");
+    std::fs::write(
+        world.source_dir.join(".lfsconfig"),
+        format!("[lfs]\n\turl = {url}\n"),
+    )
+    .expect("Failed to write .lfsconfig");
+
+    commit_and_push(&world.source_dir, ".");
+}
+
+/// Answer a single request, closing the connection afterwards.
+fn respond(stream: TcpStream, url: &str, objects: &Path, password: Option<&str>) {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+
+    let mut content_length = 0;
+    let mut authorization = None;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        let (name, value) = line.split_once(':').unwrap();
+        match name.to_ascii_lowercase().as_str() {
+            "content-length" => content_length = value.trim().parse().unwrap(),
+            "authorization" => authorization = Some(value.trim().to_owned()),
+            _ => {}
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+
+    let authorized = password.is_none_or(|password| {
+        let credentials =
+            base64::engine::general_purpose::STANDARD.encode(format!("git:{password}"));
+        authorization == Some(format!("Basic {credentials}"))
+    });
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        _ if !authorized => ("401 Unauthorized", String::new()),
+        (Some("POST"), Some("/objects/batch")) => ("200 OK", batch(url, objects, &body)),
+        (Some("GET"), Some(path)) => match path
+            .strip_prefix("/objects/")
+            .and_then(|oid| std::fs::read_to_string(objects.join(oid)).ok())
+        {
+            Some(contents) => ("200 OK", contents),
+            None => ("404 Not Found", String::new()),
+        },
+        _ => ("404 Not Found", String::new()),
+    };
+
+    write!(
+        &stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/vnd.git-lfs+json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+    .unwrap();
+}
+
+fn batch(url: &str, objects: &Path, request: &[u8]) -> String {
+    let request: serde_json::Value = serde_json::from_slice(request).unwrap();
+    let responses: Vec<_> = request["objects"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|object| {
+            let oid = object["oid"].as_str().unwrap();
+            if objects.join(oid).is_file() {
+                serde_json::json!({
+                    "oid": oid,
+                    "size": object["size"],
+                    "actions": { "download": { "href": format!("{url}/objects/{oid}") } },
+                })
+            } else {
+                serde_json::json!({
+                    "oid": oid,
+                    "size": object["size"],
+                    "error": { "code": 404, "message": "Object does not exist" },
+                })
+            }
+        })
+        .collect();
+
+    serde_json::json!({ "transfer": "basic", "objects": responses }).to_string()
+}
+
+fn credential_helper_log(world: &World) -> PathBuf {
+    world.test_dir.join("credential-helper.log")
+}
+
+fn lfs_objects(world: &World) -> PathBuf {
+    world.test_dir.join("lfs-objects")
+}
+
+fn commit_and_push(dir: &Path, path: &str) {
+    for args in [
+        vec!["add", path],
+        vec!["commit", "-m", path],
+        vec!["push", "origin", "HEAD"],
+    ] {
+        let output = std::process::Command::new("git")
+            .current_dir(dir)
+            .args(args)
+            .output()
+            .expect("Failed to run git");
+        assert!(output.status.success(), "{:?}", output);
+    }
+}
@@ -1,3 +1,20 @@
+#[cfg(feature = "async")]
+pub mod asynchronous;
 pub mod bootstrap;
+pub mod changes;
 pub mod common;
+pub mod deployment;
+pub mod dirty;
+pub mod divergence;
+#[cfg(feature = "lfs")]
+pub mod lfs;
+pub mod pin;
+pub mod rollback;
+pub mod shallow;
+pub mod signatures;
+pub mod sparse;
+pub mod ssh;
+pub mod submodules;
 pub mod sync;
+pub mod tags;
+pub mod watch;
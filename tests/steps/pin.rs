@@ -0,0 +1,61 @@
+use cucumber::{given, then};
+
+use crate::World;
+
+#[given("I pin the first remote commit")]
+fn i_pin_the_first_remote_commit(world: &mut World) {
+    let output = std::process::Command::new("git")
+        .current_dir(&world.source_dir)
+        .args(["rev-list", "--max-parents=0", "HEAD"])
+        .output()
+        .expect("Failed to find the first commit");
+    assert!(output.status.success());
+
+    world.revision = Some(
+        gitsync::Oid::from_hex(String::from_utf8_lossy(&output.stdout).trim().as_bytes())
+            .expect("a commit id"),
+    );
+}
+
+#[given(regex = r#"I pin commit "([0-9a-f]+)"$"#)]
+fn i_pin_commit(world: &mut World, commit: String) {
+    world.revision = Some(gitsync::Oid::from_hex(commit.as_bytes()).expect("a commit id"));
+}
+
+#[then("the pinned commit is checked out")]
+fn pinned_commit_is_checked_out(world: &mut World) {
+    let output = std::process::Command::new("git")
+        .current_dir(&world.clone_dir)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .expect("Failed to get current commit hash");
+    assert!(output.status.success());
+
+    assert_eq!(
+        world.revision.expect("a pinned commit").to_string(),
+        String::from_utf8_lossy(&output.stdout).trim()
+    );
+    assert_eq!(
+        "1",
+        std::fs::read_to_string(world.clone_dir.join("file")).expect("Failed to read file")
+    );
+}
+
+#[then(regex = r#"the sync reports a drift of (\d+) commits?$"#)]
+fn sync_reports_drift(world: &mut World, commits: usize) {
+    let outcome = world.sync_outcome.as_ref().expect("sync outcome");
+    let drift = outcome.drift.as_ref().expect("drift is reported");
+
+    let output = std::process::Command::new("git")
+        .current_dir(&world.source_dir)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .expect("Failed to get latest commit hash");
+    assert!(output.status.success());
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        drift.upstream.to_string()
+    );
+    assert_eq!(commits, drift.commits_ahead);
+}
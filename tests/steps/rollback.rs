@@ -0,0 +1,57 @@
+use cucumber::{then, when};
+
+use crate::steps::sync::gitsync;
+use crate::World;
+
+#[when(regex = r#"I roll back (\d+) syncs?$"#)]
+fn i_roll_back(world: &mut World, steps: usize) {
+    match gitsync(world).rollback(steps) {
+        Ok(outcome) => {
+            world.sync_outcome = Some(outcome);
+            world.sync_error = None;
+        }
+        Err(error) => {
+            world.sync_outcome = None;
+            world.sync_error = Some(error);
+        }
+    }
+}
+
+#[when("I resume syncing")]
+fn i_resume_syncing(world: &mut World) {
+    gitsync(world).resume().expect("syncing can be resumed");
+}
+
+#[then(regex = r#"the history lists (\d+) commits?$"#)]
+fn history_lists_commits(world: &mut World, count: usize) {
+    let history = gitsync(world).history().expect("history can be read");
+    let head = gitsync(world)
+        .head_oid()
+        .expect("head oid can be read")
+        .expect("head exists");
+
+    assert_eq!(count, history.len());
+    assert_eq!(Some(head), history.first().map(|record| record.commit));
+    for records in history.windows(2) {
+        assert_eq!(records[0].previous, Some(records[1].commit));
+    }
+}
+
+#[then("the latest history entry is a rollback")]
+fn latest_history_entry_is_rollback(world: &mut World) {
+    let history = gitsync(world).history().expect("history can be read");
+
+    assert!(history.first().expect("a history entry").rollback);
+    assert!(history.iter().skip(1).all(|record| !record.rollback));
+}
+
+#[then("the clone is back at the commit it started out at")]
+fn clone_is_at_first_commit(world: &mut World) {
+    let history = gitsync(world).history().expect("history can be read");
+    let head = gitsync(world)
+        .head_oid()
+        .expect("head oid can be read")
+        .expect("head exists");
+
+    assert_eq!(Some(head), history.last().map(|record| record.commit));
+}
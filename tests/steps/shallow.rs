@@ -0,0 +1,62 @@
+use cucumber::{given, then, when};
+use std::num::NonZeroU32;
+
+use crate::World;
+
+#[when(regex = r#"I bootstrap with a depth of (\d+)$"#)]
+fn bootstrap_with_depth(world: &mut World, depth: u32) {
+    let gitsync = gitsync::GitSync {
+        repo: world.repo_url.clone(),
+        dir: world.clone_dir.clone(),
+        shallow: Some(gitsync::Shallow::Depth(
+            NonZeroU32::new(depth).expect("depth isn't zero"),
+        )),
+        ..Default::default()
+    };
+
+    world.sync_error = gitsync.bootstrap().err();
+}
+
+#[given("there are local commits")]
+fn there_are_local_commits(world: &mut World) {
+    std::fs::write(world.clone_dir.join("local-file"), "1").expect("Failed to write file");
+
+    for args in [
+        vec!["add", "local-file"],
+        vec![
+            "-c",
+            "user.name=Example Author",
+            "-c",
+            "user.email=example@example.com",
+            "commit",
+            "-m",
+            "local",
+        ],
+    ] {
+        let output = std::process::Command::new("git")
+            .current_dir(&world.clone_dir)
+            .args(args)
+            .output()
+            .expect("Failed to commit locally");
+        assert!(output.status.success());
+    }
+}
+
+#[then(regex = r#"the clone is shallow with (\d+) commits?$"#)]
+fn clone_is_shallow(world: &mut World, commits: usize) {
+    let output = std::process::Command::new("git")
+        .current_dir(&world.clone_dir)
+        .args(vec!["rev-parse", "--is-shallow-repository"])
+        .output()
+        .expect("Failed to check for a shallow repository");
+    assert!(output.status.success());
+    assert_eq!(b"true\n", output.stdout.as_slice());
+
+    let output = std::process::Command::new("git")
+        .current_dir(&world.clone_dir)
+        .args(vec!["rev-list", "--count", "HEAD"])
+        .output()
+        .expect("Failed to count commits");
+    assert!(output.status.success());
+    assert_eq!(format!("{commits}\n").as_bytes(), output.stdout.as_slice());
+}
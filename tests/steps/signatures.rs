@@ -0,0 +1,189 @@
+use cucumber::given;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::World;
+
+#[given(regex = r#"I trust commits signed with SSH key "(\S+)"$"#)]
+fn i_trust_ssh_key(world: &mut World, name: String) {
+    let key = ssh_signing_key(world, &name);
+    let public_key =
+        std::fs::read_to_string(key.with_extension("pub")).expect("Failed to read public key");
+
+    let allowed_signers = world.test_dir.join("allowed_signers");
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&allowed_signers)
+        .and_then(|mut file| writeln!(file, "{name}@example.com {}", public_key.trim()))
+        .expect("Failed to write allowed signers");
+
+    world
+        .signature_policy
+        .get_or_insert_with(Default::default)
+        .allowed_signers = Some(allowed_signers);
+}
+
+#[given(regex = r#"I trust commits signed with GPG key "(\S+)"$"#)]
+fn i_trust_gpg_key(world: &mut World, name: String) {
+    gpg_signing_key(world, &name);
+
+    let output = gpg(world)
+        .args(["--export", &format!("{name}@example.com")])
+        .output()
+        .expect("Failed to export GPG key");
+    assert!(output.status.success());
+
+    let keyring = world.test_dir.join("trusted.gpg");
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&keyring)
+        .and_then(|mut file| file.write_all(&output.stdout))
+        .expect("Failed to write keyring");
+
+    world
+        .signature_policy
+        .get_or_insert_with(Default::default)
+        .keyring = Some(keyring);
+}
+
+#[given("I verify every commit")]
+fn i_verify_every_commit(world: &mut World) {
+    world
+        .signature_policy
+        .get_or_insert_with(Default::default)
+        .verify_all_commits = true;
+}
+
+#[given(regex = r#"the remote commits are signed with SSH key "(\S+)"$"#)]
+fn remote_commits_signed_with_ssh_key(world: &mut World, name: String) {
+    let key = ssh_signing_key(world, &name);
+
+    sign_remote_commits(
+        world,
+        &[
+            ("gpg.format", "ssh"),
+            ("user.signingKey", key.to_str().unwrap()),
+        ],
+    );
+}
+
+#[given(regex = r#"the remote commits are signed with GPG key "(\S+)"$"#)]
+fn remote_commits_signed_with_gpg_key(world: &mut World, name: String) {
+    gpg_signing_key(world, &name);
+
+    // git runs gpg itself, so it needs a wrapper to find the test's keys (and
+    // to not leave an agent behind)
+    let program = world.test_dir.join("gpg");
+    if !program.exists() {
+        std::fs::write(
+            &program,
+            format!(
+                "#!/bin/sh\ngpg --homedir '{home}' \"$@\"\nstatus=$?\ngpgconf --homedir '{home}' --kill gpg-agent\nexit $status\n",
+                home = gpg_home(world).display()
+            ),
+        )
+        .expect("Failed to write gpg wrapper");
+        set_mode(&program, 0o755);
+    }
+
+    sign_remote_commits(
+        world,
+        &[
+            ("gpg.format", "openpgp"),
+            ("gpg.program", program.to_str().unwrap()),
+            ("user.signingKey", &format!("{name}@example.com")),
+        ],
+    );
+}
+
+fn sign_remote_commits(world: &World, config: &[(&str, &str)]) {
+    for (key, value) in [("commit.gpgSign", "true")].iter().chain(config) {
+        let output = std::process::Command::new("git")
+            .current_dir(&world.source_dir)
+            .args(["config", key, value])
+            .output()
+            .expect("Failed to configure commit signing");
+        assert!(output.status.success());
+    }
+}
+
+fn ssh_signing_key(world: &World, name: &str) -> PathBuf {
+    let dir = world.test_dir.join("signing-keys");
+    let key = dir.join(name);
+
+    if !key.exists() {
+        std::fs::create_dir_all(&dir).expect("Failed to create signing key directory");
+        let output = std::process::Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-C", name, "-f"])
+            .arg(&key)
+            .output()
+            .expect("Failed to generate SSH signing key");
+        assert!(output.status.success());
+    }
+
+    key
+}
+
+fn gpg_signing_key(world: &World, name: &str) {
+    let home = gpg_home(world);
+    if !home.exists() {
+        std::fs::create_dir(&home).expect("Failed to create GnuPG home");
+        set_mode(&home, 0o700);
+    }
+
+    let user_id = format!("{name}@example.com");
+    let exists = gpg(world)
+        .args(["--list-keys", &user_id])
+        .output()
+        .expect("Failed to list GPG keys")
+        .status
+        .success();
+
+    if !exists {
+        let output = gpg(world)
+            .args([
+                "--batch",
+                "--pinentry-mode",
+                "loopback",
+                "--passphrase",
+                "",
+                "--quick-gen-key",
+                &format!("{name} <{user_id}>"),
+                "ed25519",
+                "sign",
+                "never",
+            ])
+            .output()
+            .expect("Failed to generate GPG signing key");
+        assert!(output.status.success(), "{:?}", output);
+        stop_gpg_agent(world);
+    }
+}
+
+fn stop_gpg_agent(world: &World) {
+    let output = std::process::Command::new("gpgconf")
+        .arg("--homedir")
+        .arg(gpg_home(world))
+        .args(["--kill", "gpg-agent"])
+        .output()
+        .expect("Failed to stop gpg-agent");
+    assert!(output.status.success());
+}
+
+fn gpg(world: &World) -> std::process::Command {
+    let mut command = std::process::Command::new("gpg");
+    command.arg("--homedir").arg(gpg_home(world));
+    command
+}
+
+fn gpg_home(world: &World) -> PathBuf {
+    world.test_dir.join("gnupg")
+}
+
+fn set_mode(path: &Path, mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .expect("Failed to set permissions");
+}
@@ -0,0 +1,25 @@
+use cucumber::{given, then};
+
+use crate::World;
+
+#[given(regex = r#"I only check out "(\S+)"$"#)]
+fn i_only_check_out(world: &mut World, directory: String) {
+    world.sparse_checkout.push(directory);
+}
+
+#[then(regex = r#"the worktree contains "(\S+)"$"#)]
+fn worktree_contains(world: &mut World, path: String) {
+    assert!(world.clone_dir.join(path).is_file());
+}
+
+#[then(regex = r#"the worktree doesn't contain "(\S+)"$"#)]
+fn worktree_does_not_contain(world: &mut World, path: String) {
+    assert!(!world.clone_dir.join(path).exists());
+}
+
+#[then(regex = r#"the worktree has the latest "(\S+)"$"#)]
+fn worktree_has_latest(world: &mut World, path: String) {
+    let expected = std::fs::read(world.source_dir.join(&path)).expect("Failed to read file");
+    let actual = std::fs::read(world.clone_dir.join(&path)).expect("Failed to read file");
+    assert_eq!(expected, actual);
+}
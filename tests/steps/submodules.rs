@@ -0,0 +1,137 @@
+use cucumber::{given, then};
+use std::path::{Path, PathBuf};
+
+use crate::World;
+
+#[given("I update submodules")]
+fn i_update_submodules(world: &mut World) {
+    world.submodules = true;
+}
+
+#[given(regex = r#"the remote has a submodule at "(\S+)"$"#)]
+fn remote_has_submodule(world: &mut World, path: String) {
+    let source_dir = world.source_dir.clone();
+    add_submodule(world, &source_dir, &path);
+    commit_and_push(&source_dir, "Add submodule");
+}
+
+#[given(regex = r#"the submodule "(\S+)" has a submodule at "(\S+)"$"#)]
+fn submodule_has_submodule(world: &mut World, submodule: String, path: String) {
+    let source_dir = submodule_source(world, &submodule);
+    add_submodule(world, &source_dir, &path);
+    commit_and_push(&source_dir, "Add submodule");
+    bump_submodule(world, &submodule);
+}
+
+#[given(regex = r#"the submodule "(\S+)" has new commits$"#)]
+fn submodule_has_new_commits(world: &mut World, submodule: String) {
+    let source_dir = submodule_source(world, &submodule);
+    let file = source_dir.join("file");
+    let contents = std::fs::read_to_string(&file).expect("Failed to read file");
+    std::fs::write(&file, contents + "2").expect("Failed to write file");
+    commit_and_push(&source_dir, "2");
+
+    bump_submodule(world, &submodule);
+}
+
+#[then(regex = r#"the submodule "(\S+)" is at its latest commit$"#)]
+fn submodule_is_at_latest_commit(world: &mut World, path: String) {
+    let name = path.rsplit('/').next().unwrap();
+    let source_dir = submodule_source(world, name);
+    let clone_dir = world.clone_dir.join(&path);
+
+    assert_eq!(rev_parse(&source_dir), rev_parse(&clone_dir));
+    assert_eq!(
+        std::fs::read_to_string(source_dir.join("file")).expect("Failed to read file"),
+        std::fs::read_to_string(clone_dir.join("file")).expect("Failed to read submodule file")
+    );
+}
+
+#[then(regex = r#"the sync reports submodule "(\S+)" (cloned|updated)$"#)]
+fn sync_reports_submodule(world: &mut World, path: String, how: String) {
+    let outcome = world.sync_outcome.as_ref().expect("sync outcome");
+    let update = outcome
+        .submodules
+        .iter()
+        .find(|update| update.path == Path::new(&path))
+        .expect("the submodule is reported");
+
+    assert_eq!(how == "cloned", update.previous.is_none());
+    assert_eq!(
+        rev_parse(&world.clone_dir.join(&path)),
+        update.current.to_string()
+    );
+}
+
+/// Create a remote for a submodule called after the last component of `path`,
+/// then add it to the repository in `dir` by a URL relative to its own.
+fn add_submodule(world: &World, dir: &Path, path: &str) {
+    let name = path.rsplit('/').next().unwrap();
+    let bare_dir = world.test_dir.join(format!("{name}.git"));
+    let source_dir = submodule_source(world, name);
+
+    git(
+        &world.test_dir,
+        &["init", "--bare", bare_dir.to_str().unwrap()],
+    );
+    git(
+        &world.test_dir,
+        &[
+            "clone",
+            bare_dir.to_str().unwrap(),
+            source_dir.to_str().unwrap(),
+        ],
+    );
+    git(&source_dir, &["config", "user.name", "Example Author"]);
+    git(
+        &source_dir,
+        &["config", "user.email", "example@example.com"],
+    );
+    std::fs::write(source_dir.join("file"), "1").expect("Failed to write file");
+    git(&source_dir, &["add", "file"]);
+    commit_and_push(&source_dir, "1");
+
+    // Every remote lives in the test directory, next to the main one
+    let origin = git(dir, &["config", "remote.origin.url"]);
+    let depth = Path::new(origin.trim())
+        .strip_prefix(&world.test_dir)
+        .expect("remotes are in the test directory")
+        .components()
+        .count();
+    let url = format!("{}{name}.git", "../".repeat(depth));
+    git(dir, &["submodule", "add", &url, path]);
+}
+
+/// Move the main remote's gitlink for `submodule` to its latest commit.
+fn bump_submodule(world: &World, submodule: &str) {
+    let checkout = world.source_dir.join(submodule);
+    git(&checkout, &["pull", "origin", "HEAD"]);
+    git(&checkout, &["submodule", "update", "--init", "--recursive"]);
+    git(&world.source_dir, &["add", submodule]);
+    commit_and_push(&world.source_dir, "Update submodule");
+}
+
+fn commit_and_push(dir: &Path, message: &str) {
+    git(dir, &["commit", "-am", message]);
+    git(dir, &["push", "origin", "HEAD"]);
+}
+
+fn submodule_source(world: &World, name: &str) -> PathBuf {
+    world.test_dir.join(format!("{name}-source"))
+}
+
+fn rev_parse(dir: &Path) -> String {
+    git(dir, &["rev-parse", "HEAD"]).trim().to_owned()
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "protocol.file.allow=always"])
+        .args(args)
+        .output()
+        .expect("Failed to run git");
+    assert!(output.status.success(), "{:?}", output);
+
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
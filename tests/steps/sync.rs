@@ -1,4 +1,5 @@
 use cucumber::{given, then, when};
+use gitsync::errors;
 
 use crate::World;
 
@@ -41,16 +42,35 @@ fn no_remote_changes(world: &mut World) {
     world.current_commit_hash = output.stdout;
 }
 
-#[when("I sync")]
-fn sync(world: &mut World) {
-    let gitsync = gitsync::GitSync {
+/// The `GitSync` that syncs the clone as the scenario has set it up.
+pub fn gitsync(world: &World) -> gitsync::GitSync {
+    gitsync::GitSync {
         repo: world.repo_url.clone(),
         dir: world.clone_dir.clone(),
         branch: world.branch.clone(),
+        cancellation: world.cancellation.clone(),
+        include: world.include.clone(),
+        exclude: world.exclude.clone(),
+        sparse_checkout: world.sparse_checkout.clone(),
+        signature_policy: world.signature_policy.clone(),
+        tag: world.tag.clone(),
+        revision: world.revision,
+        divergence: world.divergence,
+        dirty_worktree: world.dirty_worktree.clone(),
+        deployment: world.deployment.clone(),
+        submodules: world.submodules,
+        #[cfg(feature = "lfs")]
+        lfs: world.lfs,
+        password: world.password.clone().map(Into::into),
+        credential_helpers: world.credential_helpers.clone(),
+        credential_provider: world.credential_provider.clone(),
         ..Default::default()
-    };
+    }
+}
 
-    match gitsync.sync() {
+#[when("I sync")]
+fn sync(world: &mut World) {
+    match gitsync(world).sync() {
         Ok(outcome) => {
             world.sync_outcome = Some(outcome);
             world.sync_error = None;
@@ -62,6 +82,44 @@ fn sync(world: &mut World) {
     }
 }
 
+/// Run `step` with a `PATH` that has no `git` on it. Scenarios using this
+/// must be tagged `@serial`, as other scenarios need `git` meanwhile.
+///
+/// Only `git-upload-pack` is left, as that is the remote's side of a local
+/// clone or fetch rather than something gitsync runs itself.
+pub fn without_git_on_path(step: impl FnOnce()) {
+    let output = std::process::Command::new("git")
+        .arg("--exec-path")
+        .output()
+        .expect("Failed to find git's programs");
+    let exec_path = String::from_utf8(output.stdout).unwrap();
+    let upload_pack = format!("git-upload-pack{}", std::env::consts::EXE_SUFFIX);
+
+    let bin = tempfile::TempDir::new().unwrap();
+    std::fs::copy(
+        std::path::Path::new(exec_path.trim()).join(&upload_pack),
+        bin.path().join(&upload_pack),
+    )
+    .expect("Failed to copy git-upload-pack");
+
+    let path = std::env::var_os("PATH");
+    std::env::set_var("PATH", bin.path());
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(step));
+    match path {
+        Some(path) => std::env::set_var("PATH", path),
+        None => std::env::remove_var("PATH"),
+    }
+
+    if let Err(panic) = result {
+        std::panic::resume_unwind(panic);
+    }
+}
+
+#[when("I sync without git on the PATH")]
+fn sync_without_git(world: &mut World) {
+    without_git_on_path(|| sync(world));
+}
+
 #[when(regex = r#"I sync branch "(\S+)""#)]
 fn sync_branch(world: &mut World, branch: String) {
     world.branch = Some(branch);
@@ -119,12 +177,36 @@ fn sync_reports_no_changes(world: &mut World) {
     );
 }
 
+#[then("the sync reports no watched changes")]
+fn sync_reports_no_watched_changes(world: &mut World) {
+    let outcome = world.sync_outcome.as_ref().expect("sync outcome");
+    assert!(!outcome.changed);
+    assert!(outcome.changes.is_empty());
+}
+
+#[then("the clone is at the latest remote commit")]
+fn clone_is_at_latest_remote_commit(world: &mut World) {
+    let rev_parse = |dir| {
+        let output = std::process::Command::new("git")
+            .current_dir(dir)
+            .arg("rev-parse")
+            .arg("HEAD")
+            .output()
+            .expect("Failed to get current commit hash");
+        assert!(output.status.success());
+        output.stdout
+    };
+
+    assert_eq!(rev_parse(&world.source_dir), rev_parse(&world.clone_dir));
+}
+
 #[then("head_oid matches HEAD")]
 fn head_oid_matches_head(world: &mut World) {
     let gitsync = gitsync::GitSync {
         repo: world.repo_url.clone(),
         dir: world.clone_dir.clone(),
         branch: world.branch.clone(),
+        cancellation: world.cancellation.clone(),
         ..Default::default()
     };
 
@@ -157,6 +239,71 @@ fn the_sync_errors(world: &mut World) {
     assert!(world.sync_error.is_some());
 }
 
+#[then(regex = r#"the sync errors because "(.*)"$"#)]
+fn the_sync_errors_because(world: &mut World, error: String) {
+    let w = world.sync_error.as_ref().expect("sync error");
+
+    match error.as_ref() {
+        "the worktree isn't clean" => {
+            assert!(matches!(w, errors::GitSyncError::WorkTreeNotClean { .. }))
+        }
+        "cancelled" => {
+            assert!(matches!(w, errors::GitSyncError::Cancelled))
+        }
+        "insufficient history" => {
+            assert!(matches!(
+                w,
+                errors::GitSyncError::InsufficientHistory { .. }
+            ))
+        }
+        "host key mismatch" => {
+            assert!(matches!(
+                w,
+                errors::GitSyncError::HostKeyVerificationFailed { .. }
+            ))
+        }
+        "fast-forward not possible" => {
+            assert!(matches!(
+                w,
+                errors::GitSyncError::FastForwardMergeNotPossible
+            ))
+        }
+        "conflicting changes" => {
+            assert!(matches!(w, errors::GitSyncError::ConflictingChanges { .. }))
+        }
+        "no matching tag" => {
+            assert!(matches!(w, errors::GitSyncError::NoMatchingTag { .. }))
+        }
+        "unknown revision" => {
+            assert!(matches!(w, errors::GitSyncError::RevisionNotFound { .. }))
+        }
+        "rollback not possible" => {
+            assert!(matches!(
+                w,
+                errors::GitSyncError::RollbackNotPossible { .. }
+            ))
+        }
+        "LFS download failed" => {
+            assert!(matches!(w, errors::GitSyncError::LfsDownloadFailed { .. }))
+        }
+        "untrusted commit" => {
+            assert!(matches!(w, errors::GitSyncError::UntrustedCommit { .. }))
+        }
+        _ => panic!("Unknown error type"),
+    };
+}
+
+#[then(regex = r#"the sync error mentions "(.*)"$"#)]
+fn the_sync_error_mentions(world: &mut World, text: String) {
+    let error = world.sync_error.as_ref().expect("sync error").to_string();
+    assert!(
+        error.contains(&text),
+        "{:?} doesn't mention {:?}",
+        error,
+        text
+    );
+}
+
 #[given("there are local changes")]
 fn there_is_local_changes(world: &mut World) {
     let output = std::process::Command::new("git")
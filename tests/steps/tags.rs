@@ -0,0 +1,83 @@
+use cucumber::{given, then};
+use std::path::Path;
+
+use crate::World;
+
+#[given(regex = r#"the remote has a release tagged "(\S+)"$"#)]
+fn remote_has_release(world: &mut World, tag: String) {
+    std::fs::write(world.source_dir.join("version"), &tag).expect("Failed to write version");
+
+    git(&world.source_dir, &["add", "version"]);
+    git(&world.source_dir, &["commit", "-q", "-m", &tag]);
+    git(&world.source_dir, &["tag", "-a", "-m", &tag, &tag]);
+    git(&world.source_dir, &["push", "-q", "origin", "HEAD", &tag]);
+}
+
+#[given(regex = r#"the remote deletes the tag "(\S+)"$"#)]
+fn remote_deletes_tag(world: &mut World, tag: String) {
+    git(&world.source_dir, &["tag", "-d", &tag]);
+    git(
+        &world.source_dir,
+        &["push", "-q", "origin", &format!(":refs/tags/{tag}")],
+    );
+}
+
+#[given(regex = r#"I follow tag "(\S+)"$"#)]
+fn i_follow_tag(world: &mut World, tag: String) {
+    world.tag = Some(gitsync::TagSelector::Exact(tag));
+}
+
+#[given(regex = r#"I follow versions "(.+)"$"#)]
+fn i_follow_versions(world: &mut World, requirement: String) {
+    world.tag = Some(gitsync::TagSelector::SemVer(
+        requirement.parse().expect("a valid version requirement"),
+    ));
+}
+
+#[then(regex = r#"the checked out tag is "(\S+)"$"#)]
+fn checked_out_tag_is(world: &mut World, tag: String) {
+    assert_eq!(
+        rev_parse(&world.source_dir, &format!("{tag}^{{commit}}")),
+        rev_parse(&world.clone_dir, "HEAD")
+    );
+    assert_eq!(
+        b"HEAD\n".as_slice(),
+        std::process::Command::new("git")
+            .current_dir(&world.clone_dir)
+            .args(["rev-parse", "--abbrev-ref", "HEAD"])
+            .output()
+            .expect("Failed to read HEAD")
+            .stdout
+            .as_slice()
+    );
+}
+
+#[then(regex = r#"the sync reports tag "(\S+)"$"#)]
+fn sync_reports_tag(world: &mut World, tag: String) {
+    let outcome = world.sync_outcome.as_ref().expect("sync outcome");
+    assert_eq!(Some(tag), outcome.tag);
+}
+
+fn rev_parse(dir: &Path, revision: &str) -> Vec<u8> {
+    let output = std::process::Command::new("git")
+        .current_dir(dir)
+        .args(["rev-parse", revision])
+        .output()
+        .expect("Failed to parse revision");
+    assert!(output.status.success());
+    output.stdout
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let output = std::process::Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("Failed to run git");
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {:?}",
+        args,
+        output
+    );
+}
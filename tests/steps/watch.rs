@@ -0,0 +1,93 @@
+use cucumber::{given, then, when};
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+use crate::World;
+
+#[given(regex = r#"the remote is at "(\S+)"$"#)]
+fn remote_is_at(world: &mut World, url: String) {
+    world.repo_url = url;
+}
+
+#[when(regex = r#"I watch the repository until (\d+) (syncs|errors) are reported$"#)]
+fn watch(world: &mut World, count: usize, kind: String) {
+    let watcher = watcher(world);
+
+    world.watch_results.clear();
+    watcher.run(|result| {
+        world
+            .watch_results
+            .push(result.map_err(|error| error.to_string()));
+
+        if reported(world, &kind) == count {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+}
+
+#[when(regex = r#"I watch the repository over a channel until (\d+) syncs are reported$"#)]
+fn watch_over_channel(world: &mut World, count: usize) {
+    let watcher = watcher(world);
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    let handle = std::thread::spawn(move || watcher.run_with_channel(sender));
+
+    world.watch_results = receiver
+        .iter()
+        .take(count)
+        .map(|result| result.map_err(|error| error.to_string()))
+        .collect();
+
+    // Dropping the receiver stops the watcher once it next reports
+    drop(receiver);
+    handle.join().expect("watcher thread stops");
+}
+
+#[then(regex = r#"the watcher reported (\d+) (syncs|errors)$"#)]
+fn watcher_reported(world: &mut World, count: usize, kind: String) {
+    assert_eq!(count, reported(world, &kind), "{:?}", world.watch_results);
+}
+
+#[then(regex = r#"the logs mention "(.+)"$"#)]
+fn logs_mention(_world: &mut World, text: String) {
+    let logs = crate::logs();
+
+    assert!(
+        logs.iter().any(|line| line.contains(&text)),
+        "{:?} wasn't logged",
+        text
+    );
+}
+
+#[then(regex = r#"the logs don't mention "(.+)"$"#)]
+fn logs_do_not_mention(_world: &mut World, text: String) {
+    let logs = crate::logs();
+
+    assert!(
+        logs.iter().all(|line| !line.contains(&text)),
+        "{:?} was logged",
+        text
+    );
+}
+
+fn watcher(world: &World) -> gitsync::Watcher {
+    let gitsync = gitsync::GitSync {
+        repo: world.repo_url.clone(),
+        dir: world.clone_dir.clone(),
+        ..Default::default()
+    };
+
+    gitsync::Watcher::new(gitsync, Duration::from_millis(10))
+        .with_jitter(Duration::from_millis(5))
+        .with_max_backoff(Duration::from_millis(40))
+}
+
+fn reported(world: &World, kind: &str) -> usize {
+    world
+        .watch_results
+        .iter()
+        .filter(|result| result.is_ok() == (kind == "syncs"))
+        .count()
+}